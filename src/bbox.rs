@@ -1,30 +1,55 @@
-//! Fetcher adaptor for [Frebbox](https://dev.freebox.fr/sdk/os/#) french internet provider box
+//! Fetcher adaptor for [BBox](https://api.bbox.fr/doc/apirouter/index.html) french internet provider box
 
-use std::{collections::HashMap, convert::Infallible};
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::Infallible,
+    io,
+    time::{Duration, Instant, SystemTime},
+};
 
-use bytes::{Buf as _, Bytes};
+use bytes::{Buf, Bytes};
 use http::{Method, Request, Response, StatusCode};
 use http_body_util::{BodyExt as _, Full, combinators::BoxBody};
 use hyper::body::Incoming;
 use opentelemetry::KeyValue;
-use prosa::core::{adaptor::Adaptor, proc::ProcConfig};
+use prosa::core::{
+    adaptor::Adaptor,
+    msg::Tvf,
+    proc::{ProcConfig, ProcSettings as _},
+};
 use prosa_fetcher::{
     adaptor::FetcherAdaptor,
     proc::{FetchAction, FetcherError, FetcherProc, FetcherSettings},
 };
-use serde::Deserialize;
-use serde_json::{Map, Value};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::Value;
 use tokio::sync::watch;
 use tracing::{debug, warn};
 
+use crate::control::{ControlAction, ControlConfig, ControlPoller, Event};
+
 #[derive(Debug, Default, Copy, Clone, PartialEq)]
 pub enum BBoxFetchState {
+    /// Token needed for the remote access
+    Token,
+    RemoteAdmin,
+    RemoteAdminEnable,
     #[default]
+    Device,
     Cpu,
     Mem,
     Wan,
+    Xdsl,
+    XdslStats,
+    Ftth,
     Lan,
-    Wifi(bool),
+    Wireless,
+    WifiStats(u64),
+    Hosts,
+    Voip,
+    CallLog(u64),
+    /// Control action requested by the control service
+    Control,
     End,
 }
 
@@ -32,6 +57,21 @@ impl BBoxFetchState {
     /// Getter of the URI for the current Freebox call to do
     pub fn call(&self) -> Option<(Method, hyper::Uri)> {
         match self {
+            BBoxFetchState::Token => Some((
+                Method::GET,
+                "/api/v1/device/token".parse::<hyper::Uri>().unwrap(),
+            )),
+            BBoxFetchState::RemoteAdmin => Some((
+                Method::GET,
+                "/api/v1/remote/admin".parse::<hyper::Uri>().unwrap(),
+            )),
+            BBoxFetchState::RemoteAdminEnable => Some((
+                Method::PUT,
+                "/api/v1/remote/admin".parse::<hyper::Uri>().unwrap(),
+            )),
+            BBoxFetchState::Device => {
+                Some((Method::GET, "/api/v1/device".parse::<hyper::Uri>().unwrap()))
+            }
             BBoxFetchState::Cpu => Some((
                 Method::GET,
                 "/api/v1/device/cpu".parse::<hyper::Uri>().unwrap(),
@@ -44,143 +84,1350 @@ impl BBoxFetchState {
                 Method::GET,
                 "/api/v1/wan/ip/stats".parse::<hyper::Uri>().unwrap(),
             )),
+            BBoxFetchState::Xdsl => Some((
+                Method::GET,
+                "/api/v1/wan/xdsl".parse::<hyper::Uri>().unwrap(),
+            )),
+            BBoxFetchState::XdslStats => Some((
+                Method::GET,
+                "/api/v1/wan/xdsl/stats".parse::<hyper::Uri>().unwrap(),
+            )),
+            BBoxFetchState::Ftth => Some((
+                Method::GET,
+                "/api/v1/wan/ftth".parse::<hyper::Uri>().unwrap(),
+            )),
             BBoxFetchState::Lan => Some((
                 Method::GET,
                 "/api/v1/lan/stats".parse::<hyper::Uri>().unwrap(),
             )),
-            BBoxFetchState::Wifi(high) => {
-                if *high {
-                    Some((
-                        Method::GET,
-                        "/api/v1/wireless/5/stats".parse::<hyper::Uri>().unwrap(),
-                    ))
-                } else {
-                    Some((
-                        Method::GET,
-                        "/api/v1/wireless/24/stats".parse::<hyper::Uri>().unwrap(),
-                    ))
-                }
+            BBoxFetchState::Wireless => Some((
+                Method::GET,
+                "/api/v1/wireless".parse::<hyper::Uri>().unwrap(),
+            )),
+            BBoxFetchState::WifiStats(radio_id) => Some((
+                Method::GET,
+                format!("/api/v1/wireless/{radio_id}/stats")
+                    .parse::<hyper::Uri>()
+                    .unwrap(),
+            )),
+            BBoxFetchState::Hosts => {
+                Some((Method::GET, "/api/v1/hosts".parse::<hyper::Uri>().unwrap()))
+            }
+            BBoxFetchState::Voip => {
+                Some((Method::GET, "/api/v1/voip".parse::<hyper::Uri>().unwrap()))
             }
+            BBoxFetchState::CallLog(line_id) => Some((
+                Method::GET,
+                format!("/api/v1/voip/fullcalllog/{line_id}")
+                    .parse::<hyper::Uri>()
+                    .unwrap(),
+            )),
             _ => None,
         }
     }
 
-    pub fn next_state(&self) -> BBoxFetchState {
+    pub fn next_state(&self, stats: &BBoxStats) -> BBoxFetchState {
         match self {
+            BBoxFetchState::Token => BBoxFetchState::RemoteAdmin,
+            BBoxFetchState::RemoteAdmin | BBoxFetchState::RemoteAdminEnable => {
+                BBoxFetchState::Device
+            }
+            BBoxFetchState::Device => BBoxFetchState::Cpu,
             BBoxFetchState::Cpu => BBoxFetchState::Mem,
             BBoxFetchState::Mem => BBoxFetchState::Wan,
-            BBoxFetchState::Wan => BBoxFetchState::Lan,
-            BBoxFetchState::Lan => BBoxFetchState::Wifi(false),
-            BBoxFetchState::Wifi(false) => BBoxFetchState::Wifi(true),
-            BBoxFetchState::Wifi(true) => BBoxFetchState::End,
+            BBoxFetchState::Wan => BBoxFetchState::Xdsl,
+            BBoxFetchState::Xdsl => BBoxFetchState::XdslStats,
+            BBoxFetchState::XdslStats => BBoxFetchState::Ftth,
+            BBoxFetchState::Ftth => BBoxFetchState::Lan,
+            BBoxFetchState::Lan => BBoxFetchState::Wireless,
+            BBoxFetchState::Wireless => stats
+                .get_wifi_radio_ids()
+                .first()
+                .map(|radio_id| BBoxFetchState::WifiStats(*radio_id))
+                .unwrap_or(BBoxFetchState::Hosts),
+            BBoxFetchState::WifiStats(radio_id) => stats
+                .get_wifi_radio_ids()
+                .iter()
+                .skip_while(|id| *id != radio_id)
+                .nth(1)
+                .map(|next_radio_id| BBoxFetchState::WifiStats(*next_radio_id))
+                .unwrap_or(BBoxFetchState::Hosts),
+            BBoxFetchState::Hosts => BBoxFetchState::Voip,
+            BBoxFetchState::Voip => stats
+                .voip
+                .first()
+                .map(|line| BBoxFetchState::CallLog(line.id))
+                .unwrap_or(BBoxFetchState::End),
+            BBoxFetchState::CallLog(line_id) => stats
+                .voip
+                .iter()
+                .skip_while(|line| line.id != *line_id)
+                .nth(1)
+                .map(|next_line| BBoxFetchState::CallLog(next_line.id))
+                .unwrap_or(BBoxFetchState::End),
             _ => BBoxFetchState::End,
         }
     }
+
+    /// Method to know if the state is about the WAN line (only available for the line type of the BBox)
+    pub fn is_line(&self) -> bool {
+        matches!(
+            self,
+            BBoxFetchState::Xdsl | BBoxFetchState::XdslStats | BBoxFetchState::Ftth
+        )
+    }
+
+    /// Method to know if the state is optional (the endpoint may not be available on every BBox)
+    pub fn is_optional(&self) -> bool {
+        self.is_line()
+            || matches!(
+                self,
+                BBoxFetchState::RemoteAdmin
+                    | BBoxFetchState::RemoteAdminEnable
+                    | BBoxFetchState::Voip
+                    | BBoxFetchState::CallLog(_)
+            )
+    }
+}
+
+/// Parse the `BBOX_ID` session cookie, with its expiration (`Max-Age` or `Expires` attribute)
+fn parse_session_cookie(cookie: &str) -> Option<(String, Option<SystemTime>)> {
+    let mut cookie_parts = cookie.split(';');
+    let bbox_id = cookie_parts.next()?.trim().strip_prefix("BBOX_ID=")?;
+
+    let mut max_age = None;
+    let mut expires = None;
+    for attribute in cookie_parts {
+        if let Some((name, value)) = attribute.trim().split_once('=') {
+            if name.eq_ignore_ascii_case("max-age") {
+                max_age = value
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+                    .map(|age| SystemTime::now() + Duration::from_secs(age));
+            } else if name.eq_ignore_ascii_case("expires") {
+                expires = httpdate::parse_http_date(value.trim()).ok();
+            }
+        }
+    }
+
+    // `Max-Age` have precedence over `Expires`
+    Some((bbox_id.to_string(), max_age.or(expires)))
+}
+
+/// Getter of a meaningful Wi-Fi band label from the BBox radio identifier (`24`, `2.4`, `5`, `6`)
+fn wifi_band_label(band: &str) -> String {
+    match band {
+        "24" | "2.4" => "2.4GHz".to_string(),
+        "5" => "5GHz".to_string(),
+        "6" => "6GHz".to_string(),
+        band => band.to_string(),
+    }
 }
 
+/// Deserialize an unsigned number that can be sent as a string by the BBox
+fn deserialize_u64<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Value::deserialize(deserializer)? {
+        Value::Number(n) => n
+            .as_u64()
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid unsigned number {n}"))),
+        Value::String(s) => s
+            .parse()
+            .map_err(|e| serde::de::Error::custom(format!("invalid unsigned number `{s}`: {e}"))),
+        v => Err(serde::de::Error::custom(format!(
+            "expected an unsigned number, found {v}"
+        ))),
+    }
+}
+
+/// Deserialize an optional unsigned number that can be sent as a string by the BBox
+fn deserialize_opt_u64<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::Number(n)) => n.as_u64(),
+        Some(Value::String(s)) => s.parse().ok(),
+        _ => None,
+    })
+}
+
+/// Deserialize a boolean that can be sent as a number or a string by the BBox
+fn deserialize_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::Bool(b)) => b,
+        Some(Value::Number(n)) => n.as_u64().is_some_and(|n| n != 0),
+        Some(Value::String(s)) => s == "1" || s == "true",
+        _ => false,
+    })
+}
+
+/// Deserialize an optional signed number that can be sent as a string by the BBox
+fn deserialize_opt_i64<'de, D>(deserializer: D) -> Result<Option<i64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::Number(n)) => n.as_i64(),
+        Some(Value::String(s)) => s.parse().ok(),
+        _ => None,
+    })
+}
+
+/// Deserialize an optional float number that can be sent as a string by the BBox
+fn deserialize_opt_f64<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::Number(n)) => n.as_f64(),
+        Some(Value::String(s)) => s.parse().ok(),
+        _ => None,
+    })
+}
+
+/// xDSL line statistics of the BBox for a direction
 #[derive(Default, Debug, Clone, Deserialize)]
-pub struct BBoxApiResponse {
-    device: Option<HashMap<String, Value>>,
-    wan: Option<HashMap<String, Value>>,
-    lan: Option<HashMap<String, Value>>,
-    wireless: Option<HashMap<String, Value>>,
+pub struct BBoxXdslDirection {
+    /// Synchronisation rate in kb/s
+    #[serde(default, deserialize_with = "deserialize_opt_f64")]
+    pub bitrates: Option<f64>,
+    /// Signal noise ratio in 0.1 dB
+    #[serde(default, deserialize_with = "deserialize_opt_f64")]
+    pub noise: Option<f64>,
+    /// Attenuation in 0.1 dB
+    #[serde(default, deserialize_with = "deserialize_opt_f64")]
+    pub attenuation: Option<f64>,
 }
 
-impl BBoxApiResponse {
+/// xDSL line error counters of the BBox (local for the received flow, remote for the sent one)
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxXdslStats {
+    #[serde(default, deserialize_with = "deserialize_opt_f64")]
+    pub local_crc: Option<f64>,
+    #[serde(default, deserialize_with = "deserialize_opt_f64")]
+    pub local_fec: Option<f64>,
+    #[serde(default, deserialize_with = "deserialize_opt_f64")]
+    pub local_hec: Option<f64>,
+    #[serde(default, deserialize_with = "deserialize_opt_f64")]
+    pub remote_crc: Option<f64>,
+    #[serde(default, deserialize_with = "deserialize_opt_f64")]
+    pub remote_fec: Option<f64>,
+    #[serde(default, deserialize_with = "deserialize_opt_f64")]
+    pub remote_hec: Option<f64>,
+}
+
+/// xDSL line of the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxXdsl {
+    #[serde(default)]
+    pub state: String,
+    #[serde(default)]
+    pub modulation: String,
+    /// Time since the line synchronisation in seconds
+    #[serde(default, deserialize_with = "deserialize_opt_f64")]
+    pub showtime: Option<f64>,
+    #[serde(default)]
+    pub up: BBoxXdslDirection,
+    #[serde(default)]
+    pub down: BBoxXdslDirection,
+    /// Only sent by the xDSL stats endpoint
+    #[serde(default)]
+    pub stats: Option<BBoxXdslStats>,
+}
+
+/// FTTH line of the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxFtth {
+    #[serde(default)]
+    pub mode: String,
+    #[serde(default)]
+    pub state: String,
+    /// Optical received power in dBm
+    #[serde(default, deserialize_with = "deserialize_opt_f64")]
+    pub rxpower: Option<f64>,
+    /// Optical sent power in dBm
+    #[serde(default, deserialize_with = "deserialize_opt_f64")]
+    pub txpower: Option<f64>,
+}
+
+/// WAN line of the BBox, as returned by the line endpoints
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxLine {
+    #[serde(default)]
+    pub xdsl: Option<BBoxXdsl>,
+    #[serde(default)]
+    pub ftth: Option<BBoxFtth>,
+}
+
+impl BBoxLine {
+    /// Method to merge a line endpoint response into the line
     pub fn merge(&mut self, other: Self) {
-        if let Some(device_other) = other.device {
-            if let Some(device) = self.device.as_mut() {
-                device.extend(device_other);
+        if let Some(xdsl_other) = other.xdsl {
+            if let Some(xdsl) = self.xdsl.as_mut()
+                && xdsl_other.stats.is_some()
+            {
+                // Only the stats are sent by the xDSL stats endpoint
+                xdsl.stats = xdsl_other.stats;
             } else {
-                self.device = Some(device_other);
+                self.xdsl = Some(xdsl_other);
             }
         }
 
-        if let Some(wan_other) = other.wan {
-            if let Some(wan) = self.wan.as_mut() {
-                wan.extend(wan_other);
-            } else {
-                self.wan = Some(wan_other);
+        if other.ftth.is_some() {
+            self.ftth = other.ftth;
+        }
+    }
+}
+
+/// Response of the BBox line endpoints
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxLineResponse {
+    #[serde(default)]
+    pub wan: BBoxLine,
+}
+
+/// Wireless information of a BBox host
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxHostWireless {
+    #[serde(default)]
+    pub band: String,
+    /// Signal strength (dBm) of the host
+    #[serde(default, deserialize_with = "deserialize_opt_i64")]
+    pub rssi0: Option<i64>,
+}
+
+/// Host known by the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxHost {
+    #[serde(default)]
+    pub id: u64,
+    #[serde(default)]
+    pub hostname: String,
+    #[serde(default)]
+    pub macaddress: String,
+    #[serde(default)]
+    pub ipaddress: String,
+    /// `Ethernet`, `Wifi 2.4`, `Wifi 5`, `offline`, ...
+    #[serde(default)]
+    pub link: String,
+    #[serde(default)]
+    pub devicetype: String,
+    #[serde(default, deserialize_with = "deserialize_bool")]
+    pub active: bool,
+    /// Only present for wireless hosts
+    #[serde(default)]
+    pub wireless: Option<BBoxHostWireless>,
+}
+
+impl BBoxHost {
+    /// Getter of the host labels
+    pub fn get_labels(&self) -> Vec<KeyValue> {
+        vec![
+            KeyValue::new("mac", self.macaddress.clone()),
+            KeyValue::new("hostname", self.hostname.clone()),
+            KeyValue::new("link", self.link.clone()),
+        ]
+    }
+}
+
+/// List of the hosts known by the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxHosts {
+    #[serde(default)]
+    pub list: Vec<BBoxHost>,
+}
+
+/// CPU time of the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxCpuTime {
+    #[serde(deserialize_with = "deserialize_u64")]
+    pub total: u64,
+    #[serde(deserialize_with = "deserialize_u64")]
+    pub idle: u64,
+}
+
+impl BBoxCpuTime {
+    /// Getter of the CPU busy percentage since a previous sample (`None` if the counters have been reset)
+    pub fn get_busy_percent(&self, previous: &BBoxCpuTime) -> Option<f64> {
+        let total = self.total.checked_sub(previous.total)?;
+        let idle = self.idle.checked_sub(previous.idle)?;
+        if total > 0 && idle <= total {
+            Some((total - idle) as f64 * 100f64 / total as f64)
+        } else {
+            None
+        }
+    }
+}
+
+/// Process counters of the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxCpuProcess {
+    #[serde(deserialize_with = "deserialize_u64")]
+    pub created: u64,
+    #[serde(deserialize_with = "deserialize_u64")]
+    pub running: u64,
+    #[serde(deserialize_with = "deserialize_u64")]
+    pub blocked: u64,
+}
+
+/// Temperature of the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxCpuTemperature {
+    #[serde(deserialize_with = "deserialize_u64")]
+    pub main: u64,
+}
+
+/// CPU of the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxCpu {
+    pub time: BBoxCpuTime,
+    #[serde(default)]
+    pub process: Option<BBoxCpuProcess>,
+    /// Not available on every BBox model
+    #[serde(default)]
+    pub temperature: Option<BBoxCpuTemperature>,
+}
+
+/// Memory of the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxMem {
+    #[serde(deserialize_with = "deserialize_u64")]
+    pub total: u64,
+    #[serde(deserialize_with = "deserialize_u64")]
+    pub free: u64,
+    #[serde(deserialize_with = "deserialize_u64")]
+    pub cached: u64,
+}
+
+impl BBoxMem {
+    /// Getter of the used memory percentage (total - free - cached)
+    pub fn get_used_percent(&self) -> Option<f64> {
+        if self.total > 0 {
+            let used = self.total.saturating_sub(self.free + self.cached);
+            Some(used as f64 * 100f64 / self.total as f64)
+        } else {
+            None
+        }
+    }
+}
+
+/// Firmware of the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxFirmware {
+    #[serde(default)]
+    pub version: String,
+}
+
+/// Identity and firmware of the BBox
+#[derive(Debug, Clone, Deserialize)]
+pub struct BBoxDeviceInfo {
+    pub modelname: String,
+    pub serialnumber: String,
+    /// Uptime in seconds
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub uptime: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub numberofboots: Option<u64>,
+    /// Main firmware
+    #[serde(default)]
+    pub main: Option<BBoxFirmware>,
+    /// Recovery firmware
+    #[serde(default)]
+    pub reco: Option<BBoxFirmware>,
+    /// Running firmware
+    #[serde(default)]
+    pub running: Option<BBoxFirmware>,
+    /// Bootloaders
+    #[serde(default)]
+    pub bcck: Option<BBoxFirmware>,
+    #[serde(default)]
+    pub ldr1: Option<BBoxFirmware>,
+    #[serde(default)]
+    pub ldr2: Option<BBoxFirmware>,
+}
+
+impl BBoxDeviceInfo {
+    /// Getter of the known firmware versions
+    pub fn get_firmwares(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("main", &self.main),
+            ("reco", &self.reco),
+            ("running", &self.running),
+            ("bcck", &self.bcck),
+            ("ldr1", &self.ldr1),
+            ("ldr2", &self.ldr2),
+        ]
+        .into_iter()
+        .filter_map(|(name, firmware)| {
+            firmware
+                .as_ref()
+                .filter(|f| !f.version.is_empty())
+                .map(|f| (name, f.version.as_str()))
+        })
+        .collect()
+    }
+}
+
+/// Response of the BBox device endpoint
+#[derive(Debug, Clone, Deserialize)]
+pub struct BBoxDeviceResponse {
+    pub device: BBoxDeviceInfo,
+}
+
+/// Device of the BBox, as returned by the CPU and memory endpoints
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxDevice {
+    #[serde(default)]
+    pub cpu: Option<BBoxCpu>,
+    #[serde(default)]
+    pub mem: Option<BBoxMem>,
+}
+
+/// Traffic counters of the BBox for a flow
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxFlowStats {
+    #[serde(deserialize_with = "deserialize_u64")]
+    pub bytes: u64,
+    #[serde(deserialize_with = "deserialize_u64")]
+    pub packets: u64,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub packetserrors: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub packetsdiscards: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub bandwidth: Option<u64>,
+}
+
+/// Traffic counters of the BBox for an interface
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxTrafficStats {
+    pub rx: BBoxFlowStats,
+    pub tx: BBoxFlowStats,
+}
+
+/// WAN IP statistics of the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxWanIp {
+    pub stats: BBoxTrafficStats,
+}
+
+/// WAN of the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxWan {
+    pub ip: BBoxWanIp,
+}
+
+/// LAN statistics of the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxLanStats {
+    pub port: Vec<BBoxTrafficStats>,
+}
+
+/// LAN of the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxLan {
+    pub stats: BBoxLanStats,
+}
+
+/// Wireless SSID statistics of the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxSsid {
+    pub stats: BBoxTrafficStats,
+}
+
+/// Wireless statistics of a BBox radio
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxWireless {
+    pub ssid: BBoxSsid,
+}
+
+/// Response of the BBox wireless statistics endpoint
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxWirelessStatsResponse {
+    pub wireless: BBoxWireless,
+}
+
+/// Wi-Fi radio of the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxWifiRadio {
+    #[serde(default, deserialize_with = "deserialize_bool")]
+    pub enable: bool,
+    /// Wi-Fi standards (`g,n`, `a,n,ac,ax`, ...)
+    #[serde(default)]
+    pub standard: String,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub current_channel: Option<u64>,
+    /// Channel bandwidth in MHz
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub htbw: Option<u64>,
+}
+
+/// Wi-Fi radios of the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxWirelessRadios {
+    /// Radios by identifier (`24`, `5`, `6`)
+    #[serde(default)]
+    pub radio: BTreeMap<String, BBoxWifiRadio>,
+}
+
+/// Response of the BBox wireless endpoint
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxWirelessResponse {
+    #[serde(default)]
+    pub wireless: BBoxWirelessRadios,
+}
+
+/// Token of the BBox, needed for the remote access and the write calls
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxToken {
+    pub token: String,
+}
+
+/// Response of the BBox token endpoint
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxTokenResponse {
+    pub device: BBoxToken,
+}
+
+/// Remote administration access of the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxRemoteAdmin {
+    #[serde(default, deserialize_with = "deserialize_bool")]
+    pub enable: bool,
+    /// Port of the remote access
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub port: Option<u64>,
+    /// Public IP of the remote access
+    #[serde(default)]
+    pub ip: String,
+    /// Remaining duration in seconds of a temporary activation (0 if permanent)
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub duration: Option<u64>,
+}
+
+impl BBoxRemoteAdmin {
+    /// Getter of the temporary activation of the remote access (disabled automatically after its duration)
+    pub fn is_temporary(&self) -> bool {
+        self.duration.is_some_and(|duration| duration > 0)
+    }
+}
+
+/// Remote access of the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxRemote {
+    pub admin: BBoxRemoteAdmin,
+}
+
+/// VoIP line of the BBox
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxVoipLine {
+    #[serde(deserialize_with = "deserialize_u64")]
+    pub id: u64,
+    /// Registration status of the line (`Up` when registered)
+    #[serde(default)]
+    pub status: String,
+    /// `Idle`, `InCall`, `Ringing`, ...
+    #[serde(default)]
+    pub callstate: String,
+    /// Phone number of the line
+    #[serde(default)]
+    pub uri: String,
+    /// Number of voicemail messages
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub message_count: Option<u64>,
+}
+
+impl BBoxVoipLine {
+    /// Getter of the line registration (`status` equal to `Up`)
+    pub fn is_registered(&self) -> bool {
+        self.status.eq_ignore_ascii_case("up")
+    }
+
+    /// Getter of the line state (0: unregistered, 1: idle, 2: ringing, 3: in call, 4: other call state)
+    pub fn get_state(&self) -> u64 {
+        if !self.is_registered() {
+            0
+        } else if self.callstate.eq_ignore_ascii_case("idle") {
+            1
+        } else if self.callstate.eq_ignore_ascii_case("ringing") {
+            2
+        } else if self.callstate.eq_ignore_ascii_case("incall") {
+            3
+        } else {
+            4
+        }
+    }
+}
+
+/// Call of the BBox call log
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxCall {
+    #[serde(deserialize_with = "deserialize_u64")]
+    pub id: u64,
+    #[serde(default)]
+    pub number: String,
+    /// Timestamp of the call
+    #[serde(default, deserialize_with = "deserialize_u64")]
+    pub date: u64,
+    /// `in` or `out`
+    #[serde(default, rename = "type")]
+    pub call_type: String,
+    #[serde(default, deserialize_with = "deserialize_bool")]
+    pub answered: bool,
+    /// Duration of the call in seconds
+    #[serde(default, deserialize_with = "deserialize_u64")]
+    pub duree: u64,
+}
+
+impl BBoxCall {
+    /// Getter of the call type (`missed`, `accepted` or `outgoing`)
+    pub fn get_type(&self) -> &'static str {
+        if self.call_type.starts_with("out") {
+            "outgoing"
+        } else if self.answered {
+            "accepted"
+        } else {
+            "missed"
+        }
+    }
+}
+
+/// Response of the BBox API
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxApiResponse {
+    #[serde(default)]
+    device: Option<BBoxDevice>,
+    #[serde(default)]
+    wan: Option<BBoxWan>,
+    #[serde(default)]
+    lan: Option<BBoxLan>,
+    #[serde(default)]
+    hosts: Option<BBoxHosts>,
+    #[serde(default)]
+    remote: Option<BBoxRemote>,
+    #[serde(default)]
+    voip: Option<Vec<BBoxVoipLine>>,
+    #[serde(default)]
+    calllog: Option<Vec<BBoxCall>>,
+}
+
+/// Statistics of the BBox collected during a fetch
+#[derive(Default, Debug, Clone)]
+pub struct BBoxStats {
+    pub device: Option<BBoxDeviceInfo>,
+    pub cpu: Option<BBoxCpu>,
+    /// CPU busy percentage since the previous fetch
+    pub cpu_busy: Option<f64>,
+    pub mem: Option<BBoxMem>,
+    pub wan: Option<BBoxTrafficStats>,
+    pub lan: Vec<BBoxTrafficStats>,
+    /// Wi-Fi radios by identifier
+    pub wifi_radios: BTreeMap<u64, BBoxWifiRadio>,
+    /// Wireless statistics by radio identifier
+    pub wifi: BTreeMap<u64, BBoxTrafficStats>,
+    pub hosts: Option<Vec<BBoxHost>>,
+    pub line: BBoxLine,
+    pub remote_admin: Option<BBoxRemoteAdmin>,
+    pub voip: Vec<BBoxVoipLine>,
+    /// Call log by VoIP line id
+    pub calls: BTreeMap<u64, Vec<BBoxCall>>,
+    /// Number of calls by type, starting with the calls already in the call log at the start of the adaptor
+    pub calls_total: HashMap<&'static str, u64>,
+}
+
+impl BBoxStats {
+    /// Method to merge an API response into the statistics
+    pub fn merge(&mut self, api_resp: BBoxApiResponse) {
+        if let Some(device) = api_resp.device {
+            if device.cpu.is_some() {
+                self.cpu = device.cpu;
+            }
+
+            if device.mem.is_some() {
+                self.mem = device.mem;
             }
         }
 
-        if let Some(lan_other) = other.lan {
-            if let Some(lan) = self.lan.as_mut() {
-                lan.extend(lan_other);
-            } else {
-                self.lan = Some(lan_other);
+        if let Some(wan) = api_resp.wan {
+            self.wan = Some(wan.ip.stats);
+        }
+
+        if let Some(lan) = api_resp.lan {
+            self.lan = lan.stats.port;
+        }
+
+        if let Some(hosts) = api_resp.hosts {
+            self.hosts = Some(hosts.list);
+        }
+
+        if let Some(remote) = api_resp.remote {
+            self.remote_admin = Some(remote.admin);
+        }
+
+        if let Some(voip) = api_resp.voip {
+            self.voip = voip;
+        }
+    }
+
+    /// Method to merge the call log of a VoIP line
+    pub fn merge_calls(&mut self, line_id: u64, api_resp: BBoxApiResponse) {
+        if let Some(calllog) = api_resp.calllog {
+            self.calls.entry(line_id).or_default().extend(calllog);
+        }
+    }
+
+    /// Method to merge the wireless statistics of a Wi-Fi radio
+    pub fn merge_wifi_stats(&mut self, radio_id: u64, wireless_resp: BBoxWirelessStatsResponse) {
+        self.wifi
+            .insert(radio_id, wireless_resp.wireless.ssid.stats);
+    }
+
+    /// Getter of the traffic statistics with their labels (WAN, LAN ports and Wi-Fi bands)
+    pub fn get_traffic(&self) -> Vec<(Vec<KeyValue>, &BBoxTrafficStats)> {
+        let mut traffic = Vec::with_capacity(1 + self.lan.len() + self.wifi.len());
+        if let Some(wan) = &self.wan {
+            traffic.push((self.get_labels(&[KeyValue::new("type", "wan")]), wan));
+        }
+
+        for (lan_id, lan) in self.lan.iter().enumerate() {
+            traffic.push((
+                self.get_labels(&[
+                    KeyValue::new("port", lan_id.to_string()),
+                    KeyValue::new("type", "lan"),
+                ]),
+                lan,
+            ));
+        }
+
+        for (wifi_id, wifi) in &self.wifi {
+            traffic.push((
+                self.get_labels(&[
+                    KeyValue::new("band", wifi_band_label(&wifi_id.to_string())),
+                    KeyValue::new("type", "wifi"),
+                ]),
+                wifi,
+            ));
+        }
+
+        traffic
+    }
+
+    /// Getter of the labels with the BBox identity (`serial` and `model`)
+    pub fn get_labels(&self, labels: &[KeyValue]) -> Vec<KeyValue> {
+        let mut bbox_labels = Vec::with_capacity(labels.len() + 2);
+        if let Some(device) = &self.device {
+            bbox_labels.push(KeyValue::new("serial", device.serialnumber.clone()));
+            bbox_labels.push(KeyValue::new("model", device.modelname.clone()));
+        }
+        bbox_labels.extend_from_slice(labels);
+        bbox_labels
+    }
+
+    /// Method to merge the Wi-Fi radios
+    pub fn merge_radios(&mut self, radios: BBoxWirelessRadios) {
+        for (radio_id, radio) in radios.radio {
+            if let Ok(radio_id) = radio_id.parse() {
+                self.wifi_radios.insert(radio_id, radio);
             }
         }
+    }
 
-        // Merge 2.4 and 5 GHz each other on different key
-        if let Some(wireless_other) = other.wireless {
-            if let Some(wireless) = self.wireless.as_mut()
-                && let Some(ssid) = wireless_other.get("ssid").and_then(|s| s.as_object())
-                && let Some(id) = ssid.get("id").and_then(|i| i.as_number())
-            {
-                wireless.insert(id.to_string(), Value::Object(ssid.clone()));
-            } else if let Some(ssid) = wireless_other.get("ssid").and_then(|s| s.as_object())
-                && let Some(id) = ssid.get("id").and_then(|i| i.as_number())
+    /// Getter of the Wi-Fi radio identifiers to get statistics from
+    pub fn get_wifi_radio_ids(&self) -> Vec<u64> {
+        if self.wifi_radios.is_empty() {
+            // Default radios if they can't be listed
+            vec![24, 5]
+        } else {
+            self.wifi_radios.keys().copied().collect()
+        }
+    }
+
+    /// Getter of the number of active wireless hosts by band
+    pub fn get_wifi_stations(&self) -> BTreeMap<String, u64> {
+        let mut stations: BTreeMap<String, u64> = self
+            .wifi_radios
+            .keys()
+            .map(|radio_id| (wifi_band_label(&radio_id.to_string()), 0))
+            .collect();
+        for host in self.get_hosts() {
+            if host.active
+                && let Some(wireless) = &host.wireless
+                && !wireless.band.is_empty()
             {
-                let mut wireless_map = HashMap::with_capacity(2);
-                wireless_map.insert(id.to_string(), Value::Object(ssid.clone()));
-                self.wireless = Some(wireless_map);
+                *stations.entry(wifi_band_label(&wireless.band)).or_default() += 1;
+            }
+        }
+
+        stations
+    }
+
+    pub fn get_hosts(&self) -> &[BBoxHost] {
+        self.hosts.as_deref().unwrap_or_default()
+    }
+}
+
+/// Event detected by the BBox adaptor, sent to the `event_service` as a TVF message
+#[derive(Debug, Clone, PartialEq)]
+pub enum BBoxEvent {
+    /// A host became active or inactive
+    Presence {
+        mac: String,
+        hostname: String,
+        ip: String,
+        active: bool,
+    },
+    /// A new missed call is present in the call log of a VoIP line
+    MissedCall {
+        line: String,
+        number: String,
+        datetime: u64,
+    },
+}
+
+impl BBoxEvent {
+    /// TVF field of the event type (`presence` or `missed_call`)
+    pub const EVENT_TYPE: usize = 1;
+    /// TVF field of the host MAC address
+    pub const PRESENCE_MAC: usize = 2;
+    /// TVF field of the host name
+    pub const PRESENCE_HOSTNAME: usize = 3;
+    /// TVF field of the host IP address
+    pub const PRESENCE_IP: usize = 4;
+    /// TVF field of the host activity (1 for active, 0 for inactive)
+    pub const PRESENCE_ACTIVE: usize = 5;
+    /// TVF field of the VoIP line number that missed the call
+    pub const CALL_LINE: usize = 2;
+    /// TVF field of the caller number
+    pub const CALL_NUMBER: usize = 3;
+    /// TVF field of the call timestamp
+    pub const CALL_DATETIME: usize = 4;
+}
+
+impl Event for BBoxEvent {
+    fn to_tvf<M>(&self) -> M
+    where
+        M: Tvf + std::default::Default,
+    {
+        let mut msg = M::default();
+        match self {
+            BBoxEvent::Presence {
+                mac,
+                hostname,
+                ip,
+                active,
+            } => {
+                msg.put_string(BBoxEvent::EVENT_TYPE, "presence");
+                msg.put_string(BBoxEvent::PRESENCE_MAC, mac);
+                msg.put_string(BBoxEvent::PRESENCE_HOSTNAME, hostname);
+                msg.put_string(BBoxEvent::PRESENCE_IP, ip);
+                msg.put_unsigned(BBoxEvent::PRESENCE_ACTIVE, *active as u64);
+            }
+            BBoxEvent::MissedCall {
+                line,
+                number,
+                datetime,
+            } => {
+                msg.put_string(BBoxEvent::EVENT_TYPE, "missed_call");
+                msg.put_string(BBoxEvent::CALL_LINE, line);
+                msg.put_string(BBoxEvent::CALL_NUMBER, number);
+                msg.put_unsigned(BBoxEvent::CALL_DATETIME, *datetime);
             }
         }
+
+        msg
     }
+}
+
+/// Method to know if the value is a MAC address (`aa:bb:cc:dd:ee:ff`)
+fn is_mac_address(mac: &str) -> bool {
+    mac.split(':').count() == 6
+        && mac
+            .split(':')
+            .all(|byte| byte.len() == 2 && byte.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Control action requested to the BBox by the control service
+#[derive(Debug, Clone, PartialEq)]
+pub enum BBoxControl {
+    /// Enable or disable the parental control
+    Parental(bool),
+    /// Enable or disable the parental control rule of a host.
+    /// The host is restricted by the parental control schedule while its rule is enabled.
+    ParentalHost { mac: String, enabled: bool },
+}
+
+impl BBoxControl {
+    /// TVF field to enable (1) or disable (0) the parental control
+    pub const PARENTAL_ENABLED: usize = 2;
+    /// TVF field of the MAC address of the host
+    pub const PARENTAL_HOST_MAC: usize = 2;
+    /// TVF field to enable (1) or disable (0) the parental control rule of the host
+    pub const PARENTAL_HOST_ENABLED: usize = 3;
 
-    pub fn take(&mut self) -> Self {
-        Self {
-            device: self.device.take(),
-            wan: self.wan.take(),
-            lan: self.lan.take(),
-            wireless: self.wireless.take(),
+    /// Getter of the BBox call to do for the action, with its form body
+    pub fn call(&self) -> (Method, hyper::Uri, String) {
+        match self {
+            BBoxControl::Parental(enabled) => (
+                Method::PUT,
+                "/api/v1/parentalcontrol".parse::<hyper::Uri>().unwrap(),
+                format!("enable={}", *enabled as u8),
+            ),
+            BBoxControl::ParentalHost { mac, enabled } => (
+                Method::PUT,
+                "/api/v1/parentalcontrol/hosts"
+                    .parse::<hyper::Uri>()
+                    .unwrap(),
+                format!("macaddress={mac}&enable={}", *enabled as u8),
+            ),
         }
     }
+}
 
-    pub fn get_wan_stats(&self) -> Option<&Map<String, Value>> {
-        self.wan.as_ref().and_then(|w| {
-            w.get("ip")
-                .and_then(|ip| ip.get("stats").and_then(|s| s.as_object()))
-        })
+impl ControlAction for BBoxControl {
+    type Config = BBoxControlConfig;
+
+    fn parse<M>(action: &str, msg: &M) -> Result<BBoxControl, String>
+    where
+        M: Tvf,
+    {
+        match action {
+            "parental" => Ok(BBoxControl::Parental(
+                msg.get_unsigned(BBoxControl::PARENTAL_ENABLED)
+                    .map_err(|e| format!("invalid parental control enabled field: {e}"))?
+                    != 0,
+            )),
+            "parental_host" => Ok(BBoxControl::ParentalHost {
+                mac: msg
+                    .get_string(BBoxControl::PARENTAL_HOST_MAC)
+                    .map_err(|e| format!("invalid parental host MAC field: {e}"))
+                    .and_then(|mac| {
+                        // The MAC address is sent in the form body of the call
+                        if is_mac_address(&mac) {
+                            Ok(mac.to_lowercase())
+                        } else {
+                            Err(format!("invalid parental host MAC address `{mac}`"))
+                        }
+                    })?,
+                enabled: msg
+                    .get_unsigned(BBoxControl::PARENTAL_HOST_ENABLED)
+                    .map_err(|e| format!("invalid parental host enabled field: {e}"))?
+                    != 0,
+            }),
+            action => Err(format!("unknown action `{action}`")),
+        }
     }
 
-    pub fn get_lan_stats(&self) -> Option<Vec<&Map<String, Value>>> {
-        self.lan.as_ref().and_then(|l| {
-            l.get("stats").and_then(|s| {
-                s.get("port").and_then(|p| {
-                    p.as_array()
-                        .and_then(|a| a.iter().map(|l| l.as_object()).collect())
-                })
-            })
-        })
+    fn get_name(&self) -> &'static str {
+        match self {
+            BBoxControl::Parental(_) => "parental",
+            BBoxControl::ParentalHost { .. } => "parental_host",
+        }
     }
 
-    pub fn get_wifi_stats(&self) -> Option<Vec<&Map<String, Value>>> {
-        self.wireless
-            .as_ref()
-            .and_then(|w| w.values().map(|s| s.as_object()).collect())
+    fn is_allowed(&self, config: &BBoxControlConfig) -> bool {
+        match self {
+            BBoxControl::Parental(_) => config.parental,
+            BBoxControl::ParentalHost { mac, .. } => config
+                .parental_hosts
+                .iter()
+                .any(|allowed_mac| allowed_mac.eq_ignore_ascii_case(mac)),
+        }
     }
+}
 
-    pub fn parse_u64(v: &Value) -> Option<u64> {
-        match v {
-            Value::Number(n) => n.as_u64(),
-            Value::String(s) => s.parse().ok(),
-            _ => None,
+/// BBox control actions allowed, every action is disabled by default
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxControlConfig {
+    /// Allow to enable or disable the parental control
+    #[serde(default)]
+    pub parental: bool,
+    /// MAC addresses of the hosts whose parental control rule can be enabled or disabled
+    #[serde(default)]
+    pub parental_hosts: Vec<String>,
+}
+
+/// Configuration of the BBox remote access (API reached from internet)
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct BBoxRemoteConfig {
+    /// Renew the remote access when it's only temporary enabled on the BBox
+    #[serde(default)]
+    pub keep_enabled: bool,
+}
+
+/// Configuration of the BBox adaptor (loaded from the processor `adaptor_config_path`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct BBoxAdaptorConfig {
+    /// Service where the BBox events (presence, missed calls, ...) are sent
+    pub event_service: Option<String>,
+    /// Maximum number of consecutive failed logins before returning an authentication error.
+    /// The login is then retried with the delay of the last attempt.
+    #[serde(default = "BBoxAdaptorConfig::get_default_max_login_attempts")]
+    pub max_login_attempts: u32,
+    /// Delay in seconds before the first login retry, doubled for every failed login
+    #[serde(default = "BBoxAdaptorConfig::get_default_login_retry_delay")]
+    pub login_retry_delay: u64,
+    /// Remote access mode, to reach the BBox API from internet
+    pub remote: Option<BBoxRemoteConfig>,
+    /// Control actions allowed on the BBox
+    pub control: Option<ControlConfig<BBoxControlConfig>>,
+}
+
+impl BBoxAdaptorConfig {
+    fn get_default_max_login_attempts() -> u32 {
+        5
+    }
+
+    fn get_default_login_retry_delay() -> u64 {
+        10
+    }
+
+    /// Getter of the delay before the next login, after the given number of failed logins
+    pub fn get_login_retry_delay(&self, login_attempts: u32) -> Duration {
+        Duration::from_secs(
+            self.login_retry_delay
+                .saturating_mul(2u64.saturating_pow(login_attempts.saturating_sub(1))),
+        )
+    }
+}
+
+impl Default for BBoxAdaptorConfig {
+    fn default() -> Self {
+        BBoxAdaptorConfig {
+            event_service: None,
+            max_login_attempts: Self::get_default_max_login_attempts(),
+            login_retry_delay: Self::get_default_login_retry_delay(),
+            remote: None,
+            control: None,
         }
     }
 }
 
-/// Adaptor for [Freebox](https://dev.freebox.fr/sdk/os/#) french internet provider box
+/// Adaptor for [BBox](https://api.bbox.fr/doc/apirouter/index.html) french internet provider box
 #[derive(Adaptor)]
 pub struct FetcherBBoxAdaptor {
     settings: FetcherSettings,
+    config: BBoxAdaptorConfig,
     bbox_id: Option<String>,
+    /// Expiration of the `BBOX_ID` session cookie
+    bbox_id_expiry: Option<SystemTime>,
+    /// Number of consecutive failed logins
+    login_attempts: u32,
+    /// Time before which the login must not be retried
+    login_retry: Option<Instant>,
+    /// Token sent as `btoken` query parameter in remote access mode and for write calls
+    btoken: Option<String>,
+    /// Control action in progress
+    control: Option<BBoxControl>,
     state: BBoxFetchState,
-    stats: BBoxApiResponse,
+    stats: BBoxStats,
+    /// CPU time of the previous fetch, to compute the CPU usage
+    previous_cpu_time: Option<BBoxCpuTime>,
+    /// Activity of the hosts by MAC address (`None` before the first fetch)
+    hosts_active: Option<HashMap<String, bool>>,
+    /// Last call id seen by VoIP line id (absent before the first fetch of the line)
+    last_call_ids: HashMap<u64, u64>,
+    /// Number of calls by type, starting with the calls already in the call log at the start of the adaptor
+    calls_total: HashMap<&'static str, u64>,
+    /// Events and control results to send, with the polling of the control service
+    poller: ControlPoller<BBoxEvent, BBoxControl>,
 
     // Observability
-    meter_bbox: watch::Sender<BBoxApiResponse>,
+    meter_bbox: watch::Sender<BBoxStats>,
+}
+
+impl FetcherBBoxAdaptor {
+    /// Margin to login again before the expiration of the session cookie
+    const SESSION_RENEW_MARGIN: Duration = Duration::from_secs(60);
+
+    /// Method to know if the session cookie expire soon, and need to be renewed
+    fn is_session_expiring(&self) -> bool {
+        self.bbox_id_expiry
+            .is_some_and(|expiry| SystemTime::now() + Self::SESSION_RENEW_MARGIN >= expiry)
+    }
+
+    /// Method to handle a failed login.
+    /// The login is retried later with an exponential backoff, capped once the maximum number of attempts is reached.
+    fn process_login_failure<M>(
+        &mut self,
+        reason: String,
+    ) -> Result<FetchAction<M>, FetcherError<M>>
+    where
+        M: std::marker::Send,
+    {
+        self.bbox_id = None;
+        self.bbox_id_expiry = None;
+        self.login_attempts += 1;
+        if self.login_attempts >= self.config.max_login_attempts {
+            // Keep retrying with the last delay, without flooding the BBox with logins
+            self.login_retry = Some(
+                Instant::now()
+                    + self
+                        .config
+                        .get_login_retry_delay(self.config.max_login_attempts),
+            );
+            Err(FetcherError::Io(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "BBox authentication failed {} times ({reason}), check the password of the URL",
+                    self.login_attempts
+                ),
+            )))
+        } else {
+            let retry_delay = self.config.get_login_retry_delay(self.login_attempts);
+            warn!(
+                "BBox login failed ({reason}), attempt {}/{}: retry in {}s",
+                self.login_attempts,
+                self.config.max_login_attempts,
+                retry_delay.as_secs()
+            );
+            self.login_retry = Some(Instant::now() + retry_delay);
+            Ok(FetchAction::None)
+        }
+    }
+
+    /// Method to detect the presence change of hosts
+    fn detect_presence(&mut self, hosts: &[BBoxHost]) {
+        let hosts_active: HashMap<String, bool> = hosts
+            .iter()
+            .map(|host| (host.macaddress.clone(), host.active))
+            .collect();
+
+        if let Some(previous_hosts_active) = &self.hosts_active {
+            for host in hosts {
+                let was_active = previous_hosts_active
+                    .get(&host.macaddress)
+                    .copied()
+                    .unwrap_or_default();
+                if was_active != host.active {
+                    debug!(
+                        "BBox host {} ({}) active: {}",
+                        host.hostname, host.macaddress, host.active
+                    );
+                    self.poller.push_event(BBoxEvent::Presence {
+                        mac: host.macaddress.clone(),
+                        hostname: host.hostname.clone(),
+                        ip: host.ipaddress.clone(),
+                        active: host.active,
+                    });
+                }
+            }
+        }
+
+        self.hosts_active = Some(hosts_active);
+    }
+
+    /// Method to detect the new calls of the VoIP lines
+    fn detect_calls(&mut self, voip: &[BBoxVoipLine], calls: &BTreeMap<u64, Vec<BBoxCall>>) {
+        for (line_id, line_calls) in calls {
+            let last_call_id = self.last_call_ids.get(line_id).copied();
+            let mut new_calls: Vec<&BBoxCall> = line_calls
+                .iter()
+                .filter(|call| last_call_id.is_none_or(|id| call.id > id))
+                .collect();
+            new_calls.sort_by_key(|call| call.id);
+
+            if let Some(last_call) = new_calls.last() {
+                self.last_call_ids.insert(*line_id, last_call.id);
+                for call in &new_calls {
+                    *self.calls_total.entry(call.get_type()).or_default() += 1;
+                }
+
+                // Don't notify the calls already in the log at startup
+                if last_call_id.is_some() {
+                    let line = voip
+                        .iter()
+                        .find(|line| line.id == *line_id)
+                        .map(|line| line.uri.clone())
+                        .unwrap_or_else(|| line_id.to_string());
+                    for call in new_calls {
+                        if call.get_type() == "missed" {
+                            debug!("BBox new missed call from {} on {}", call.number, line);
+                            self.poller.push_event(BBoxEvent::MissedCall {
+                                line: line.clone(),
+                                number: call.number.clone(),
+                                datetime: call.date,
+                            });
+                        }
+                    }
+                }
+            } else if last_call_id.is_none() {
+                // Empty call log
+                self.last_call_ids.insert(*line_id, 0);
+            }
+        }
+    }
+
+    /// Method to parse the API response of the current state
+    fn parse_response<T, M>(&self, body: impl Buf) -> Result<T, FetcherError<M>>
+    where
+        T: DeserializeOwned,
+        M: std::marker::Send,
+    {
+        serde_json::from_reader(body.reader()).map_err(|e| {
+            FetcherError::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Can't parse BBox API[{:?}] response: {e}", self.state),
+            ))
+        })
+    }
+
+    /// Method to go to the next state, and publish the statistics once every call is made
+    fn process_next_state<M>(&mut self) -> FetchAction<M>
+    where
+        M: Tvf + std::default::Default + std::marker::Send,
+    {
+        self.state = self.state.next_state(&self.stats);
+        if self.state != BBoxFetchState::End {
+            // Call for next state
+            FetchAction::Http
+        } else {
+            // Every call have been made
+            let mut stats = std::mem::take(&mut self.stats);
+            if let Some(cpu) = &stats.cpu {
+                stats.cpu_busy = self
+                    .previous_cpu_time
+                    .as_ref()
+                    .and_then(|previous| cpu.time.get_busy_percent(previous));
+                self.previous_cpu_time = Some(cpu.time.clone());
+            }
+            if stats.hosts.is_some() {
+                self.detect_presence(stats.get_hosts());
+            }
+            self.detect_calls(&stats.voip, &stats.calls);
+            stats.calls_total = self.calls_total.clone();
+            let _ = self.meter_bbox.send(stats);
+            self.poller.next_action()
+        }
+    }
+
+    /// Method to process a control action received from the control service
+    fn process_control<M>(&mut self, control: BBoxControl) -> FetchAction<M>
+    where
+        M: Tvf + std::default::Default + std::marker::Send,
+    {
+        self.control = Some(control);
+        // Write calls need a fresh token
+        self.btoken = None;
+        self.state = BBoxFetchState::Token;
+        FetchAction::Http
+    }
+
+    /// Method to send the result of a control action to the control service
+    fn process_control_result<M>(&mut self, control: BBoxControl, success: bool) -> FetchAction<M>
+    where
+        M: Tvf + std::default::Default + std::marker::Send,
+    {
+        self.state = BBoxFetchState::End;
+        self.poller.push_result(&control, success);
+        self.poller.next_action()
+    }
 }
 
 impl<M> FetcherAdaptor<M> for FetcherBBoxAdaptor
@@ -198,7 +1445,15 @@ where
     where
         Self: std::marker::Sized,
     {
-        let (meter_bbox, watch_bbox) = watch::channel(BBoxApiResponse::default());
+        let config = if proc.settings.get_adaptor_config_path().is_some() {
+            proc.settings
+                .get_adaptor_config::<BBoxAdaptorConfig>()
+                .map_err(|e| FetcherError::Other(format!("Can't read BBox adaptor config: {e}")))?
+        } else {
+            BBoxAdaptorConfig::default()
+        };
+
+        let (meter_bbox, watch_bbox) = watch::channel(BBoxStats::default());
 
         let watch_system = watch_bbox.clone();
         let _observable_temp = proc
@@ -207,480 +1462,598 @@ where
             .u64_observable_gauge("prosa_bbox_system")
             .with_description("System information of the BBox")
             .with_callback(move |observer| {
-                if let Some(device) = &watch_system.borrow().device {
-                    if let Some(cpu) = device.get("cpu").and_then(|c| c.as_object()) {
-                        if let Some(time) = cpu.get("time") {
-                            if let Some(total) =
-                                time.get("total").and_then(BBoxApiResponse::parse_u64)
-                            {
-                                observer.observe(
-                                    total,
-                                    &[KeyValue::new("type", "cpu"), KeyValue::new("time", "total")],
-                                );
-                            }
+                let stats = watch_system.borrow();
+                if let Some(cpu) = &stats.cpu {
+                    observer.observe(
+                        cpu.time.total,
+                        &stats.get_labels(&[
+                            KeyValue::new("type", "cpu"),
+                            KeyValue::new("time", "total"),
+                        ]),
+                    );
+                    observer.observe(
+                        cpu.time.idle,
+                        &stats.get_labels(&[
+                            KeyValue::new("type", "cpu"),
+                            KeyValue::new("time", "idle"),
+                        ]),
+                    );
 
-                            if let Some(idle) =
-                                time.get("idle").and_then(BBoxApiResponse::parse_u64)
-                            {
-                                observer.observe(
-                                    idle,
-                                    &[KeyValue::new("type", "cpu"), KeyValue::new("time", "idle")],
-                                );
-                            }
+                    if let Some(process) = &cpu.process {
+                        for (process_type, count) in [
+                            ("created", process.created),
+                            ("running", process.running),
+                            ("blocked", process.blocked),
+                        ] {
+                            observer.observe(
+                                count,
+                                &stats.get_labels(&[
+                                    KeyValue::new("type", "process"),
+                                    KeyValue::new("process", process_type),
+                                ]),
+                            );
                         }
+                    }
 
-                        if let Some(process) = cpu.get("process") {
-                            if let Some(created) =
-                                process.get("created").and_then(BBoxApiResponse::parse_u64)
-                            {
-                                observer.observe(
-                                    created,
-                                    &[
-                                        KeyValue::new("type", "process"),
-                                        KeyValue::new("process", "created"),
-                                    ],
-                                );
-                            }
+                    if let Some(temp) = &cpu.temperature {
+                        observer.observe(
+                            temp.main,
+                            &stats.get_labels(&[
+                                KeyValue::new("type", "temp"),
+                                KeyValue::new("temp", "main"),
+                            ]),
+                        );
+                    }
+                }
 
-                            if let Some(running) =
-                                process.get("running").and_then(BBoxApiResponse::parse_u64)
-                            {
-                                observer.observe(
-                                    running,
-                                    &[
-                                        KeyValue::new("type", "process"),
-                                        KeyValue::new("process", "running"),
-                                    ],
-                                );
-                            }
+                if let Some(mem) = &stats.mem {
+                    for (mem_type, size) in [
+                        ("total", mem.total),
+                        ("free", mem.free),
+                        ("cached", mem.cached),
+                    ] {
+                        observer.observe(
+                            size,
+                            &stats.get_labels(&[
+                                KeyValue::new("type", "mem"),
+                                KeyValue::new("mem", mem_type),
+                            ]),
+                        );
+                    }
+                }
+            })
+            .build();
 
-                            if let Some(blocked) =
-                                process.get("blocked").and_then(BBoxApiResponse::parse_u64)
-                            {
-                                observer.observe(
-                                    blocked,
-                                    &[
-                                        KeyValue::new("type", "process"),
-                                        KeyValue::new("process", "blocked"),
-                                    ],
-                                );
-                            }
-                        }
+        let watch_uptime = watch_bbox.clone();
+        let _observable_uptime = proc
+            .get_proc_param()
+            .meter("bbox")
+            .u64_observable_gauge("prosa_bbox_uptime_seconds")
+            .with_description("Uptime (s) of the BBox")
+            .with_callback(move |observer| {
+                let stats = watch_uptime.borrow();
+                if let Some(uptime) = stats.device.as_ref().and_then(|d| d.uptime) {
+                    observer.observe(uptime, &stats.get_labels(&[]));
+                }
+            })
+            .build();
 
-                        if let Some(temp) = cpu.get("temperature")
-                            && let Some(main) =
-                                temp.get("main").and_then(BBoxApiResponse::parse_u64)
-                        {
-                            observer.observe(
-                                main,
-                                &[KeyValue::new("type", "temp"), KeyValue::new("temp", "main")],
-                            );
-                        }
+        let watch_boots = watch_bbox.clone();
+        let _observable_boots = proc
+            .get_proc_param()
+            .meter("bbox")
+            .u64_observable_counter("prosa_bbox_boots")
+            .with_description("Number of boots of the BBox")
+            .with_callback(move |observer| {
+                let stats = watch_boots.borrow();
+                if let Some(boots) = stats.device.as_ref().and_then(|d| d.numberofboots) {
+                    observer.observe(boots, &stats.get_labels(&[]));
+                }
+            })
+            .build();
+
+        let watch_firmware = watch_bbox.clone();
+        let _observable_firmware = proc
+            .get_proc_param()
+            .meter("bbox")
+            .u64_observable_gauge("prosa_bbox_firmware_info")
+            .with_description("Firmware versions of the BBox")
+            .with_callback(move |observer| {
+                let stats = watch_firmware.borrow();
+                if let Some(device) = &stats.device {
+                    for (firmware, version) in device.get_firmwares() {
+                        observer.observe(
+                            1,
+                            &stats.get_labels(&[
+                                KeyValue::new("firmware", firmware),
+                                KeyValue::new("version", version.to_string()),
+                            ]),
+                        );
                     }
+                }
+            })
+            .build();
 
-                    if let Some(mem) = device.get("mem").and_then(|c| c.as_object()) {
-                        if let Some(total) = mem.get("total").and_then(BBoxApiResponse::parse_u64) {
-                            observer.observe(
-                                total,
-                                &[KeyValue::new("type", "mem"), KeyValue::new("mem", "total")],
-                            );
-                        }
+        let watch_cpu_usage = watch_bbox.clone();
+        let _observable_cpu_usage = proc
+            .get_proc_param()
+            .meter("bbox")
+            .f64_observable_gauge("prosa_bbox_cpu_usage")
+            .with_description("CPU busy percentage of the BBox since the previous fetch")
+            .with_callback(move |observer| {
+                let stats = watch_cpu_usage.borrow();
+                if let Some(cpu_busy) = stats.cpu_busy {
+                    observer.observe(cpu_busy, &stats.get_labels(&[]));
+                }
+            })
+            .build();
 
-                        if let Some(free) = mem.get("free").and_then(BBoxApiResponse::parse_u64) {
-                            observer.observe(
-                                free,
-                                &[KeyValue::new("type", "mem"), KeyValue::new("mem", "free")],
-                            );
-                        }
+        let watch_mem_usage = watch_bbox.clone();
+        let _observable_mem_usage = proc
+            .get_proc_param()
+            .meter("bbox")
+            .f64_observable_gauge("prosa_bbox_mem_usage")
+            .with_description("Used memory percentage of the BBox (total - free - cached)")
+            .with_callback(move |observer| {
+                let stats = watch_mem_usage.borrow();
+                if let Some(mem_used) = stats.mem.as_ref().and_then(BBoxMem::get_used_percent) {
+                    observer.observe(mem_used, &stats.get_labels(&[]));
+                }
+            })
+            .build();
 
-                        if let Some(cached) = mem.get("cached").and_then(BBoxApiResponse::parse_u64)
-                        {
-                            observer.observe(
-                                cached,
-                                &[KeyValue::new("type", "mem"), KeyValue::new("mem", "cached")],
-                            );
-                        }
+        let watch_bytes = watch_bbox.clone();
+        let _observable_rate = proc
+            .get_proc_param()
+            .meter("bbox")
+            .u64_observable_counter("prosa_bbox_bytes")
+            .with_description("BBox bytes counter")
+            .with_callback(move |observer| {
+                let stats = watch_bytes.borrow();
+                for (labels, traffic) in stats.get_traffic() {
+                    for (flow, flow_stats) in [("recv", &traffic.rx), ("send", &traffic.tx)] {
+                        observer.observe(
+                            flow_stats.bytes,
+                            &[labels.as_slice(), &[KeyValue::new("flow", flow)]].concat(),
+                        );
                     }
                 }
             })
             .build();
 
-        let watch_bytes = watch_bbox.clone();
-        let _observable_rate =
-            proc.get_proc_param()
-                .meter("bbox")
-                .u64_observable_counter("prosa_bbox_bytes")
-                .with_description("BBox bytes counter")
-                .with_callback(move |observer| {
-                    let bbox_api = watch_bytes.borrow();
-                    if let Some(wan_stats) = bbox_api.get_wan_stats() {
-                        if let Some(rx) = wan_stats
-                            .get("rx")
-                            .and_then(|r| r.get("bytes").and_then(BBoxApiResponse::parse_u64))
-                        {
+        let watch_packets = watch_bbox.clone();
+        let _observable_rate = proc
+            .get_proc_param()
+            .meter("bbox")
+            .u64_observable_counter("prosa_bbox_packets")
+            .with_description("BBox packets counter")
+            .with_callback(move |observer| {
+                let stats = watch_packets.borrow();
+                for (labels, traffic) in stats.get_traffic() {
+                    for (flow, flow_stats) in [("recv", &traffic.rx), ("send", &traffic.tx)] {
+                        observer.observe(
+                            flow_stats.packets,
+                            &[labels.as_slice(), &[KeyValue::new("flow", flow)]].concat(),
+                        );
+
+                        if let Some(errors) = flow_stats.packetserrors {
                             observer.observe(
-                                rx,
-                                &[KeyValue::new("type", "wan"), KeyValue::new("flow", "recv")],
+                                errors,
+                                &[
+                                    labels.as_slice(),
+                                    &[KeyValue::new("flow", format!("{flow}_err"))],
+                                ]
+                                .concat(),
                             );
                         }
 
-                        if let Some(tx) = wan_stats
-                            .get("tx")
-                            .and_then(|t| t.get("bytes").and_then(BBoxApiResponse::parse_u64))
-                        {
+                        if let Some(discards) = flow_stats.packetsdiscards {
                             observer.observe(
-                                tx,
-                                &[KeyValue::new("type", "wan"), KeyValue::new("flow", "send")],
+                                discards,
+                                &[
+                                    labels.as_slice(),
+                                    &[KeyValue::new("flow", format!("{flow}_discard"))],
+                                ]
+                                .concat(),
                             );
                         }
                     }
+                }
+            })
+            .build();
 
-                    if let Some(lan_stats) = bbox_api.get_lan_stats() {
-                        for (lan_id, lan) in lan_stats.iter().enumerate() {
-                            if let Some(rx) = lan
-                                .get("rx")
-                                .and_then(|r| r.get("bytes").and_then(BBoxApiResponse::parse_u64))
-                            {
-                                observer.observe(
-                                    rx,
-                                    &[
-                                        KeyValue::new("port", lan_id.to_string()),
-                                        KeyValue::new("type", "lan"),
-                                        KeyValue::new("flow", "recv"),
-                                    ],
-                                );
-                            }
+        let watch_hosts = watch_bbox.clone();
+        let _observable_host_active = proc
+            .get_proc_param()
+            .meter("bbox")
+            .u64_observable_gauge("prosa_bbox_host_active")
+            .with_description("Activity of the hosts known by the BBox (1 for active, 0 otherwise)")
+            .with_callback(move |observer| {
+                let stats = watch_hosts.borrow();
+                for host in stats.get_hosts() {
+                    observer.observe(host.active as u64, &stats.get_labels(&host.get_labels()));
+                }
+            })
+            .build();
 
-                            if let Some(tx) = lan
-                                .get("tx")
-                                .and_then(|t| t.get("bytes").and_then(BBoxApiResponse::parse_u64))
-                            {
-                                observer.observe(
-                                    tx,
-                                    &[
-                                        KeyValue::new("port", lan_id.to_string()),
-                                        KeyValue::new("type", "lan"),
-                                        KeyValue::new("flow", "send"),
-                                    ],
-                                );
-                            }
-                        }
+        let watch_hosts_rssi = watch_bbox.clone();
+        let _observable_host_rssi = proc
+            .get_proc_param()
+            .meter("bbox")
+            .i64_observable_gauge("prosa_bbox_host_rssi")
+            .with_description("Signal strength (dBm) of the wireless hosts of the BBox")
+            .with_callback(move |observer| {
+                let stats = watch_hosts_rssi.borrow();
+                for host in stats.get_hosts() {
+                    if host.active
+                        && let Some(wireless) = &host.wireless
+                        && let Some(rssi) = wireless.rssi0
+                    {
+                        let mut labels = host.get_labels();
+                        labels.push(KeyValue::new("band", wifi_band_label(&wireless.band)));
+                        observer.observe(rssi, &stats.get_labels(&labels));
                     }
+                }
+            })
+            .build();
 
-                    if let Some(wifi_stats) = bbox_api.get_wifi_stats() {
-                        for wifi in wifi_stats {
-                            if let (Some(wifi_id), Some(wifi_stat)) = (
-                                wifi.get("id").map(|i| i.to_string()),
-                                wifi.get("stats").and_then(|s| s.as_object()),
-                            ) {
-                                if let Some(rx) = wifi_stat.get("rx").and_then(|r| {
-                                    r.get("bytes").and_then(BBoxApiResponse::parse_u64)
-                                }) {
-                                    observer.observe(
-                                        rx,
-                                        &[
-                                            KeyValue::new("band", wifi_id.clone()),
-                                            KeyValue::new("type", "wifi"),
-                                            KeyValue::new("flow", "recv"),
-                                        ],
-                                    );
-                                }
+        let watch_wifi_radio = watch_bbox.clone();
+        let _observable_wifi_radio = proc
+            .get_proc_param()
+            .meter("bbox")
+            .u64_observable_gauge("prosa_bbox_wifi_radio")
+            .with_description("State of the BBox Wi-Fi radios (1 for enabled, 0 otherwise)")
+            .with_callback(move |observer| {
+                let stats = watch_wifi_radio.borrow();
+                for (radio_id, radio) in &stats.wifi_radios {
+                    observer.observe(
+                        radio.enable as u64,
+                        &stats.get_labels(&[
+                            KeyValue::new("band", wifi_band_label(&radio_id.to_string())),
+                            KeyValue::new("standard", radio.standard.clone()),
+                        ]),
+                    );
+                }
+            })
+            .build();
 
-                                if let Some(tx) = wifi_stat.get("tx").and_then(|t| {
-                                    t.get("bytes").and_then(BBoxApiResponse::parse_u64)
-                                }) {
-                                    observer.observe(
-                                        tx,
-                                        &[
-                                            KeyValue::new("band", wifi_id),
-                                            KeyValue::new("type", "wifi"),
-                                            KeyValue::new("flow", "send"),
-                                        ],
-                                    );
-                                }
-                            }
-                        }
+        let watch_wifi_channel = watch_bbox.clone();
+        let _observable_wifi_channel = proc
+            .get_proc_param()
+            .meter("bbox")
+            .u64_observable_gauge("prosa_bbox_wifi_channel")
+            .with_description("Channel and bandwidth (MHz) of the BBox Wi-Fi radios")
+            .with_callback(move |observer| {
+                let stats = watch_wifi_channel.borrow();
+                for (radio_id, radio) in &stats.wifi_radios {
+                    let band = wifi_band_label(&radio_id.to_string());
+                    if let Some(channel) = radio.current_channel {
+                        observer.observe(
+                            channel,
+                            &stats.get_labels(&[
+                                KeyValue::new("band", band.clone()),
+                                KeyValue::new("type", "channel"),
+                            ]),
+                        );
                     }
-                })
-                .build();
 
-        let watch_packets = watch_bbox.clone();
-        let _observable_rate =
-            proc.get_proc_param()
-                .meter("bbox")
-                .u64_observable_counter("prosa_bbox_packets")
-                .with_description("BBox packets counter")
-                .with_callback(move |observer| {
-                    let bbox_api = watch_packets.borrow();
-                    if let Some(wan_stats) = bbox_api.get_wan_stats() {
-                        if let Some(rx) = wan_stats
-                            .get("rx")
-                            .and_then(|r| r.get("packets").and_then(BBoxApiResponse::parse_u64))
-                        {
-                            observer.observe(
-                                rx,
-                                &[KeyValue::new("type", "wan"), KeyValue::new("flow", "recv")],
-                            );
-                        }
+                    if let Some(bandwidth) = radio.htbw {
+                        observer.observe(
+                            bandwidth,
+                            &stats.get_labels(&[
+                                KeyValue::new("band", band),
+                                KeyValue::new("type", "bandwidth"),
+                            ]),
+                        );
+                    }
+                }
+            })
+            .build();
+
+        let watch_wifi_stations = watch_bbox.clone();
+        let _observable_wifi_stations = proc
+            .get_proc_param()
+            .meter("bbox")
+            .u64_observable_gauge("prosa_bbox_wifi_stations")
+            .with_description("Number of stations associated to the BBox Wi-Fi by band")
+            .with_callback(move |observer| {
+                let stats = watch_wifi_stations.borrow();
+                if stats.hosts.is_some() {
+                    for (band, count) in stats.get_wifi_stations() {
+                        observer.observe(count, &stats.get_labels(&[KeyValue::new("band", band)]));
+                    }
+                }
+            })
+            .build();
+
+        let watch_remote_admin = watch_bbox.clone();
+        let _observable_remote_admin = proc
+            .get_proc_param()
+            .meter("bbox")
+            .u64_observable_gauge("prosa_bbox_remote_admin")
+            .with_description("Remote access of the BBox (1 for enabled, 0 otherwise)")
+            .with_callback(move |observer| {
+                let stats = watch_remote_admin.borrow();
+                if let Some(remote_admin) = &stats.remote_admin {
+                    observer.observe(
+                        remote_admin.enable as u64,
+                        &stats.get_labels(&[
+                            KeyValue::new("ip", remote_admin.ip.clone()),
+                            KeyValue::new(
+                                "port",
+                                remote_admin
+                                    .port
+                                    .map(|port| port.to_string())
+                                    .unwrap_or_default(),
+                            ),
+                        ]),
+                    );
+                }
+            })
+            .build();
+
+        let watch_voip = watch_bbox.clone();
+        let _observable_voip = proc
+            .get_proc_param()
+            .meter("bbox")
+            .u64_observable_gauge("prosa_bbox_voip_status")
+            .with_description(
+                "Status of the BBox VoIP lines (0: unregistered, 1: idle, 2: ringing, 3: in call, 4: other call state)",
+            )
+            .with_callback(move |observer| {
+                let stats = watch_voip.borrow();
+                for line in &stats.voip {
+                    observer.observe(
+                        line.get_state(),
+                        &stats.get_labels(&[
+                            KeyValue::new("line", line.id.to_string()),
+                            KeyValue::new("number", line.uri.clone()),
+                        ]),
+                    );
+                }
+            })
+            .build();
 
-                        if let Some(rx) = wan_stats.get("rx").and_then(|r| {
-                            r.get("packetserrors").and_then(BBoxApiResponse::parse_u64)
-                        }) {
-                            observer.observe(
-                                rx,
-                                &[
-                                    KeyValue::new("type", "wan"),
-                                    KeyValue::new("flow", "recv_err"),
-                                ],
-                            );
-                        }
+        let watch_calls = watch_bbox.clone();
+        let _observable_calls = proc
+            .get_proc_param()
+            .meter("bbox")
+            .u64_observable_counter("prosa_bbox_calls_total")
+            .with_description(
+                "Number of calls on the BBox VoIP lines, including the call log at the adaptor start",
+            )
+            .with_callback(move |observer| {
+                let stats = watch_calls.borrow();
+                for (call_type, count) in &stats.calls_total {
+                    observer.observe(
+                        *count,
+                        &stats.get_labels(&[KeyValue::new("type", *call_type)]),
+                    );
+                }
+            })
+            .build();
 
-                        if let Some(rx) = wan_stats.get("rx").and_then(|r| {
-                            r.get("packetsdiscards")
-                                .and_then(BBoxApiResponse::parse_u64)
-                        }) {
+        let watch_line_snr = watch_bbox.clone();
+        let _observable_xdsl_snr = proc
+            .get_proc_param()
+            .meter("bbox")
+            .f64_observable_gauge("prosa_bbox_xdsl_snr")
+            .with_description("Signal noise ratio (dB) of the BBox xDSL line")
+            .with_callback(move |observer| {
+                let stats = watch_line_snr.borrow();
+                if let Some(xdsl) = &stats.line.xdsl {
+                    for (flow, direction) in [("send", &xdsl.up), ("recv", &xdsl.down)] {
+                        if let Some(noise) = direction.noise {
                             observer.observe(
-                                rx,
-                                &[
-                                    KeyValue::new("type", "wan"),
-                                    KeyValue::new("flow", "recv_discard"),
-                                ],
+                                noise / 10f64,
+                                &stats.get_labels(&[KeyValue::new("flow", flow)]),
                             );
                         }
+                    }
+                }
+            })
+            .build();
 
-                        if let Some(tx) = wan_stats
-                            .get("tx")
-                            .and_then(|t| t.get("packets").and_then(BBoxApiResponse::parse_u64))
-                        {
+        let watch_line_attn = watch_bbox.clone();
+        let _observable_xdsl_attn = proc
+            .get_proc_param()
+            .meter("bbox")
+            .f64_observable_gauge("prosa_bbox_xdsl_attenuation")
+            .with_description("Attenuation (dB) of the BBox xDSL line")
+            .with_callback(move |observer| {
+                let stats = watch_line_attn.borrow();
+                if let Some(xdsl) = &stats.line.xdsl {
+                    for (flow, direction) in [("send", &xdsl.up), ("recv", &xdsl.down)] {
+                        if let Some(attenuation) = direction.attenuation {
                             observer.observe(
-                                tx,
-                                &[KeyValue::new("type", "wan"), KeyValue::new("flow", "send")],
+                                attenuation / 10f64,
+                                &stats.get_labels(&[KeyValue::new("flow", flow)]),
                             );
                         }
+                    }
+                }
+            })
+            .build();
 
-                        if let Some(tx) = wan_stats.get("tx").and_then(|t| {
-                            t.get("packetserrors").and_then(BBoxApiResponse::parse_u64)
-                        }) {
+        let watch_line_rate = watch_bbox.clone();
+        let _observable_xdsl_rate = proc
+            .get_proc_param()
+            .meter("bbox")
+            .f64_observable_gauge("prosa_bbox_xdsl_rate")
+            .with_description("Synchronisation rate (kb/s) of the BBox xDSL line")
+            .with_callback(move |observer| {
+                let stats = watch_line_rate.borrow();
+                if let Some(xdsl) = &stats.line.xdsl {
+                    for (flow, direction) in [("send", &xdsl.up), ("recv", &xdsl.down)] {
+                        if let Some(bitrates) = direction.bitrates {
                             observer.observe(
-                                tx,
-                                &[
-                                    KeyValue::new("type", "wan"),
-                                    KeyValue::new("flow", "send_err"),
-                                ],
+                                bitrates,
+                                &stats.get_labels(&[KeyValue::new("flow", flow)]),
                             );
                         }
+                    }
+                }
+            })
+            .build();
 
-                        if let Some(tx) = wan_stats.get("tx").and_then(|t| {
-                            t.get("packetsdiscards")
-                                .and_then(BBoxApiResponse::parse_u64)
-                        }) {
+        let watch_line_errors = watch_bbox.clone();
+        let _observable_xdsl_errors = proc
+            .get_proc_param()
+            .meter("bbox")
+            .f64_observable_counter("prosa_bbox_xdsl_errors")
+            .with_description("Errors counter of the BBox xDSL line")
+            .with_callback(move |observer| {
+                let stats = watch_line_errors.borrow();
+                if let Some(xdsl_stats) = stats.line.xdsl.as_ref().and_then(|x| x.stats.as_ref()) {
+                    for (flow, error_type, count) in [
+                        ("recv", "crc", xdsl_stats.local_crc),
+                        ("recv", "fec", xdsl_stats.local_fec),
+                        ("recv", "hec", xdsl_stats.local_hec),
+                        ("send", "crc", xdsl_stats.remote_crc),
+                        ("send", "fec", xdsl_stats.remote_fec),
+                        ("send", "hec", xdsl_stats.remote_hec),
+                    ] {
+                        if let Some(count) = count {
                             observer.observe(
-                                tx,
-                                &[
-                                    KeyValue::new("type", "wan"),
-                                    KeyValue::new("flow", "send_discard"),
-                                ],
+                                count,
+                                &stats.get_labels(&[
+                                    KeyValue::new("flow", flow),
+                                    KeyValue::new("type", error_type),
+                                ]),
                             );
                         }
                     }
+                }
+            })
+            .build();
 
-                    if let Some(lan_stats) = bbox_api.get_lan_stats() {
-                        for (lan_id, lan) in lan_stats.iter().enumerate() {
-                            if let Some(rx) = lan
-                                .get("rx")
-                                .and_then(|r| r.get("packets").and_then(BBoxApiResponse::parse_u64))
-                            {
-                                observer.observe(
-                                    rx,
-                                    &[
-                                        KeyValue::new("port", lan_id.to_string()),
-                                        KeyValue::new("type", "lan"),
-                                        KeyValue::new("flow", "recv"),
-                                    ],
-                                );
-                            }
-
-                            if let Some(tx) = lan
-                                .get("tx")
-                                .and_then(|t| t.get("packets").and_then(BBoxApiResponse::parse_u64))
-                            {
-                                observer.observe(
-                                    tx,
-                                    &[
-                                        KeyValue::new("port", lan_id.to_string()),
-                                        KeyValue::new("type", "lan"),
-                                        KeyValue::new("flow", "send"),
-                                    ],
-                                );
-                            }
-                        }
-                    }
-
-                    if let Some(wifi_stats) = bbox_api.get_wifi_stats() {
-                        for wifi in wifi_stats {
-                            if let (Some(wifi_id), Some(wifi_stat)) = (
-                                wifi.get("id").map(|i| i.to_string()),
-                                wifi.get("stats").and_then(|s| s.as_object()),
-                            ) {
-                                if let Some(rx) = wifi_stat.get("rx").and_then(|r| {
-                                    r.get("packets").and_then(BBoxApiResponse::parse_u64)
-                                }) {
-                                    observer.observe(
-                                        rx,
-                                        &[
-                                            KeyValue::new("band", wifi_id.clone()),
-                                            KeyValue::new("type", "wifi"),
-                                            KeyValue::new("flow", "recv"),
-                                        ],
-                                    );
-                                }
-
-                                if let Some(rx) = wifi_stat.get("rx").and_then(|r| {
-                                    r.get("packetserrors").and_then(BBoxApiResponse::parse_u64)
-                                }) {
-                                    observer.observe(
-                                        rx,
-                                        &[
-                                            KeyValue::new("band", wifi_id.clone()),
-                                            KeyValue::new("type", "wifi"),
-                                            KeyValue::new("flow", "recv_err"),
-                                        ],
-                                    );
-                                }
-
-                                if let Some(rx) = wifi_stat.get("rx").and_then(|r| {
-                                    r.get("packetsdiscards")
-                                        .and_then(BBoxApiResponse::parse_u64)
-                                }) {
-                                    observer.observe(
-                                        rx,
-                                        &[
-                                            KeyValue::new("band", wifi_id.clone()),
-                                            KeyValue::new("type", "wifi"),
-                                            KeyValue::new("flow", "recv_discard"),
-                                        ],
-                                    );
-                                }
+        let watch_line_uptime = watch_bbox.clone();
+        let _observable_xdsl_uptime = proc
+            .get_proc_param()
+            .meter("bbox")
+            .f64_observable_gauge("prosa_bbox_xdsl_uptime")
+            .with_description("Time (s) since the BBox xDSL line synchronisation")
+            .with_callback(move |observer| {
+                let stats = watch_line_uptime.borrow();
+                if let Some(xdsl) = &stats.line.xdsl
+                    && let Some(showtime) = xdsl.showtime
+                {
+                    observer.observe(
+                        showtime,
+                        &stats.get_labels(&[
+                            KeyValue::new("state", xdsl.state.clone()),
+                            KeyValue::new("modulation", xdsl.modulation.clone()),
+                        ]),
+                    );
+                }
+            })
+            .build();
 
-                                if let Some(tx) = wifi_stat.get("tx").and_then(|t| {
-                                    t.get("packets").and_then(BBoxApiResponse::parse_u64)
-                                }) {
-                                    observer.observe(
-                                        tx,
-                                        &[
-                                            KeyValue::new("band", wifi_id.clone()),
-                                            KeyValue::new("type", "wifi"),
-                                            KeyValue::new("flow", "send"),
-                                        ],
-                                    );
-                                }
+        let watch_line_ftth = watch_bbox.clone();
+        let _observable_ftth_status = proc
+            .get_proc_param()
+            .meter("bbox")
+            .u64_observable_gauge("prosa_bbox_ftth_status")
+            .with_description("Status of the BBox FTTH line (1 for up, 0 otherwise)")
+            .with_callback(move |observer| {
+                let stats = watch_line_ftth.borrow();
+                if let Some(ftth) = &stats.line.ftth {
+                    observer.observe(
+                        ftth.state.eq_ignore_ascii_case("up") as u64,
+                        &stats.get_labels(&[KeyValue::new("mode", ftth.mode.clone())]),
+                    );
+                }
+            })
+            .build();
 
-                                if let Some(tx) = wifi_stat.get("tx").and_then(|t| {
-                                    t.get("packetserrors").and_then(BBoxApiResponse::parse_u64)
-                                }) {
-                                    observer.observe(
-                                        tx,
-                                        &[
-                                            KeyValue::new("band", wifi_id.clone()),
-                                            KeyValue::new("type", "wifi"),
-                                            KeyValue::new("flow", "send_err"),
-                                        ],
-                                    );
-                                }
+        let watch_line_power = watch_bbox.clone();
+        let _observable_ftth_power = proc
+            .get_proc_param()
+            .meter("bbox")
+            .f64_observable_gauge("prosa_bbox_ftth_power")
+            .with_description("Optical power (dBm) of the BBox FTTH line")
+            .with_callback(move |observer| {
+                let stats = watch_line_power.borrow();
+                if let Some(ftth) = &stats.line.ftth {
+                    if let Some(txpower) = ftth.txpower {
+                        observer
+                            .observe(txpower, &stats.get_labels(&[KeyValue::new("flow", "send")]));
+                    }
 
-                                if let Some(tx) = wifi_stat.get("tx").and_then(|t| {
-                                    t.get("packetsdiscards")
-                                        .and_then(BBoxApiResponse::parse_u64)
-                                }) {
-                                    observer.observe(
-                                        tx,
-                                        &[
-                                            KeyValue::new("band", wifi_id),
-                                            KeyValue::new("type", "wifi"),
-                                            KeyValue::new("flow", "send_discard"),
-                                        ],
-                                    );
-                                }
-                            }
-                        }
+                    if let Some(rxpower) = ftth.rxpower {
+                        observer
+                            .observe(rxpower, &stats.get_labels(&[KeyValue::new("flow", "recv")]));
                     }
-                })
-                .build();
-
-        let watch_bandwidth = watch_bbox.clone();
-        let _observable_rate =
-            proc.get_proc_param()
-                .meter("bbox")
-                .u64_observable_gauge("prosa_bbox_bandwidth")
-                .with_description("BBox bandwidth gauge")
-                .with_callback(move |observer| {
-                    let bbox_api = watch_bandwidth.borrow();
-                    if let Some(wan_stats) = bbox_api.get_wan_stats() {
-                        if let Some(rx) = wan_stats
-                            .get("rx")
-                            .and_then(|r| r.get("bandwidth").and_then(BBoxApiResponse::parse_u64))
-                        {
-                            observer.observe(
-                                rx,
-                                &[KeyValue::new("type", "wan"), KeyValue::new("flow", "recv")],
-                            );
-                        }
+                }
+            })
+            .build();
 
-                        if let Some(tx) = wan_stats
-                            .get("tx")
-                            .and_then(|t| t.get("bandwidth").and_then(BBoxApiResponse::parse_u64))
-                        {
+        let _observable_rate = proc
+            .get_proc_param()
+            .meter("bbox")
+            .u64_observable_gauge("prosa_bbox_bandwidth")
+            .with_description("BBox bandwidth gauge")
+            .with_callback(move |observer| {
+                let stats = watch_bbox.borrow();
+                for (labels, traffic) in stats.get_traffic() {
+                    for (flow, flow_stats) in [("recv", &traffic.rx), ("send", &traffic.tx)] {
+                        if let Some(bandwidth) = flow_stats.bandwidth {
                             observer.observe(
-                                tx,
-                                &[KeyValue::new("type", "wan"), KeyValue::new("flow", "send")],
+                                bandwidth,
+                                &[labels.as_slice(), &[KeyValue::new("flow", flow)]].concat(),
                             );
                         }
                     }
+                }
+            })
+            .build();
 
-                    if let Some(lan_stats) = bbox_api.get_lan_stats() {
-                        for (lan_id, lan) in lan_stats.iter().enumerate() {
-                            if let Some(rx) = lan.get("rx").and_then(|r| {
-                                r.get("bandwidth").and_then(BBoxApiResponse::parse_u64)
-                            }) {
-                                observer.observe(
-                                    rx,
-                                    &[
-                                        KeyValue::new("port", lan_id.to_string()),
-                                        KeyValue::new("type", "lan"),
-                                        KeyValue::new("flow", "recv"),
-                                    ],
-                                );
-                            }
-
-                            if let Some(tx) = lan.get("tx").and_then(|t| {
-                                t.get("bandwidth").and_then(BBoxApiResponse::parse_u64)
-                            }) {
-                                observer.observe(
-                                    tx,
-                                    &[
-                                        KeyValue::new("port", lan_id.to_string()),
-                                        KeyValue::new("type", "lan"),
-                                        KeyValue::new("flow", "send"),
-                                    ],
-                                );
-                            }
-                        }
-                    }
-                })
-                .build();
-
+        let poller =
+            ControlPoller::new("BBox", config.event_service.clone(), config.control.clone());
         Ok(Self {
             settings: proc.settings.clone(),
+            config,
             bbox_id: None,
+            bbox_id_expiry: None,
+            login_attempts: 0,
+            login_retry: None,
+            btoken: None,
+            control: None,
             state: BBoxFetchState::End,
-            stats: BBoxApiResponse::default(),
+            stats: BBoxStats::default(),
+            previous_cpu_time: None,
+            hosts_active: None,
+            last_call_ids: HashMap::new(),
+            calls_total: HashMap::new(),
+            poller,
             meter_bbox,
         })
     }
 
     fn fetch(&mut self) -> Result<FetchAction<M>, FetcherError<M>> {
+        if let Some(login_retry) = self.login_retry
+            && Instant::now() < login_retry
+        {
+            debug!("BBox login retry delayed after failed logins");
+            return Ok(FetchAction::None);
+        }
+
+        if self.bbox_id.is_some() && self.is_session_expiring() {
+            debug!("BBox session expire soon, login again");
+            self.bbox_id = None;
+            self.bbox_id_expiry = None;
+        }
+
         // Call HTTP to retrieve statistics with first state
-        self.state = BBoxFetchState::default();
+        self.control = None;
+        self.poller.reset();
+        self.btoken = None;
+        self.state = if self.config.remote.is_some() {
+            // A new token is needed for the remote access
+            BBoxFetchState::Token
+        } else {
+            BBoxFetchState::default()
+        };
         Ok(FetchAction::Http)
     }
 
@@ -705,8 +2078,40 @@ where
                 ))
             }
         } else if let Some(bbox_id) = &self.bbox_id
-            && let Some((method, uri)) = self.state.call()
+            && self.state == BBoxFetchState::Control
+            && let Some(control) = &self.control
+            && let Some(btoken) = &self.btoken
+        {
+            // Write call protected by the token
+            let (method, uri, body) = control.call();
+            request_builder = request_builder
+                .method(method)
+                .uri(
+                    format!("{}?btoken={btoken}", uri.path())
+                        .parse::<hyper::Uri>()
+                        .map_err(|e| FetcherError::Other(format!("Invalid BBox token: {e}")))?,
+                )
+                .header(hyper::header::CONNECTION, "keep-alive")
+                .header(hyper::header::ACCEPT, "application/json")
+                .header(
+                    hyper::header::CONTENT_TYPE,
+                    "application/x-www-form-urlencoded",
+                )
+                .header(hyper::header::COOKIE, format!("BBOX_ID={}", bbox_id));
+            let request = request_builder.body(BoxBody::new(Full::new(Bytes::from(body))))?;
+            Ok(request)
+        } else if let Some(bbox_id) = &self.bbox_id
+            && let Some((method, mut uri)) = self.state.call()
         {
+            if self.state != BBoxFetchState::Token
+                && let Some(btoken) = &self.btoken
+            {
+                // The remote access and the write calls need the token
+                uri = format!("{}?btoken={btoken}", uri.path())
+                    .parse::<hyper::Uri>()
+                    .map_err(|e| FetcherError::Other(format!("Invalid BBox token: {e}")))?;
+            }
+
             // Send request depending of the state
             request_builder = request_builder
                 .method(method)
@@ -714,7 +2119,16 @@ where
                 .header(hyper::header::CONNECTION, "keep-alive")
                 .header(hyper::header::ACCEPT, "application/json")
                 .header(hyper::header::COOKIE, format!("BBOX_ID={}", bbox_id));
-            let request = request_builder.body(BoxBody::default())?;
+            let request = if self.state == BBoxFetchState::RemoteAdminEnable {
+                request_builder
+                    .header(
+                        hyper::header::CONTENT_TYPE,
+                        "application/x-www-form-urlencoded",
+                    )
+                    .body(BoxBody::new(Full::new(Bytes::from("enable=1"))))?
+            } else {
+                request_builder.body(BoxBody::default())?
+            };
             Ok(request)
         } else {
             Err(FetcherError::Other(
@@ -735,16 +2149,16 @@ where
                             for cookie in
                                 response.headers().get_all(hyper::header::SET_COOKIE).iter()
                             {
-                                if let Ok(Some(bbox_id)) =
-                                    cookie.to_str().map(|c| c.strip_prefix("BBOX_ID="))
+                                if let Some((bbox_id, bbox_id_expiry)) =
+                                    cookie.to_str().ok().and_then(parse_session_cookie)
                                 {
-                                    self.bbox_id = Some(
-                                        bbox_id.split(';').next().unwrap_or(bbox_id).to_string(),
-                                    );
+                                    self.bbox_id = Some(bbox_id);
+                                    self.bbox_id_expiry = bbox_id_expiry;
                                 }
                             }
 
                             if self.bbox_id.is_some() {
+                                self.login_retry = None;
                                 // Go for next call
                                 Ok(FetchAction::Http)
                             } else {
@@ -753,6 +2167,9 @@ where
                                 ))
                             }
                         }
+                        code @ (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => {
+                            self.process_login_failure(format!("rejected credentials {code}"))
+                        }
                         code => Err(FetcherError::Other(format!(
                             "Receive error from HTTP remote for login: {code}"
                         ))),
@@ -770,28 +2187,117 @@ where
                                 .map_err(|e| FetcherError::Hyper(e, server.unwrap_or_default()))?
                                 .aggregate();
 
+                            // The session is valid
+                            self.login_attempts = 0;
+
                             // Parse the API response return to get the data
-                            let api_resp: Vec<BBoxApiResponse> =
-                                serde_json::from_reader(body.reader())
-                                    .map_err(|e| FetcherError::Io(e.into()))?;
-                            for bbox_api in api_resp {
-                                self.stats.merge(bbox_api);
+                            if self.state == BBoxFetchState::Token {
+                                let token_resp: Vec<BBoxTokenResponse> =
+                                    self.parse_response(body)?;
+                                self.btoken = token_resp
+                                    .into_iter()
+                                    .next()
+                                    .map(|bbox_token| bbox_token.device.token);
+                                if self.btoken.is_none() {
+                                    return Err(FetcherError::Other(
+                                        "Can't retrieve the BBox token".to_string(),
+                                    ));
+                                } else if self.control.is_some() {
+                                    // Do the requested control action with the token
+                                    self.state = BBoxFetchState::Control;
+                                    return Ok(FetchAction::Http);
+                                }
+                            } else if self.state == BBoxFetchState::Control
+                                && let Some(control) = self.control.take()
+                            {
+                                return Ok(self.process_control_result(control, true));
+                            } else if self.state == BBoxFetchState::RemoteAdminEnable {
+                                debug!("BBox remote access renewed");
+                            } else if self.state.is_line() {
+                                let line_resp: Vec<BBoxLineResponse> = self.parse_response(body)?;
+                                for bbox_line in line_resp {
+                                    self.stats.line.merge(bbox_line.wan);
+                                }
+                            } else if let BBoxFetchState::CallLog(line_id) = self.state {
+                                let api_resp: Vec<BBoxApiResponse> = self.parse_response(body)?;
+                                for bbox_api in api_resp {
+                                    self.stats.merge_calls(line_id, bbox_api);
+                                }
+                            } else if self.state == BBoxFetchState::Device {
+                                let device_resp: Vec<BBoxDeviceResponse> =
+                                    self.parse_response(body)?;
+                                if let Some(bbox_device) = device_resp.into_iter().next() {
+                                    self.stats.device = Some(bbox_device.device);
+                                }
+                            } else if let BBoxFetchState::WifiStats(radio_id) = self.state {
+                                let wireless_resp: Vec<BBoxWirelessStatsResponse> =
+                                    self.parse_response(body)?;
+                                for bbox_wireless in wireless_resp {
+                                    self.stats.merge_wifi_stats(radio_id, bbox_wireless);
+                                }
+                            } else if self.state == BBoxFetchState::Wireless {
+                                let wireless_resp: Vec<BBoxWirelessResponse> =
+                                    self.parse_response(body)?;
+                                for bbox_wireless in wireless_resp {
+                                    self.stats.merge_radios(bbox_wireless.wireless);
+                                }
+                            } else {
+                                let api_resp: Vec<BBoxApiResponse> = self.parse_response(body)?;
+                                for bbox_api in api_resp {
+                                    self.stats.merge(bbox_api);
+                                }
+
+                                if self.state == BBoxFetchState::RemoteAdmin
+                                    && self
+                                        .config
+                                        .remote
+                                        .as_ref()
+                                        .is_some_and(|remote| remote.keep_enabled)
+                                    && self
+                                        .stats
+                                        .remote_admin
+                                        .as_ref()
+                                        .is_some_and(|remote_admin| remote_admin.is_temporary())
+                                {
+                                    // Renew the temporary remote access before it's disabled
+                                    self.state = BBoxFetchState::RemoteAdminEnable;
+                                    return Ok(FetchAction::Http);
+                                }
                             }
 
-                            self.state = self.state.next_state();
-                            if self.state != BBoxFetchState::End {
-                                // Call for next state
+                            Ok(self.process_next_state())
+                        }
+                        StatusCode::UNAUTHORIZED => {
+                            if self.login_attempts == 0 {
+                                // Ask for a new token (it may expired)
+                                self.bbox_id = None;
+                                self.bbox_id_expiry = None;
+                                self.login_attempts = 1;
+                                if self.config.remote.is_some() || self.control.is_some() {
+                                    // The token is linked to the session
+                                    self.btoken = None;
+                                    self.state = BBoxFetchState::Token;
+                                }
                                 Ok(FetchAction::Http)
                             } else {
-                                // Every call have been made
-                                let _ = self.meter_bbox.send(self.stats.take());
-                                Ok(FetchAction::None)
+                                self.process_login_failure(
+                                    "session rejected after login".to_string(),
+                                )
                             }
                         }
-                        StatusCode::UNAUTHORIZED => {
-                            self.bbox_id = None;
-                            // Ask for a new token (it may expired)
-                            Ok(FetchAction::Http)
+                        code if self.state == BBoxFetchState::Control
+                            && let Some(control) = self.control.take() =>
+                        {
+                            warn!(
+                                "BBox control action `{}` failed: {code}",
+                                control.get_name()
+                            );
+                            Ok(self.process_control_result(control, false))
+                        }
+                        code if self.state.is_optional() => {
+                            // The endpoint is not available for the line type or the services of the BBox
+                            debug!("BBox API[{:?}] not available: {code}", self.state);
+                            Ok(self.process_next_state())
                         }
                         code => Err(FetcherError::Other(format!(
                             "Receive error from HTTP remote: {code}"
@@ -811,4 +2317,739 @@ where
             Err(e) => Err(e),
         }
     }
+
+    fn process_service_response(
+        &mut self,
+        mut response: prosa::core::msg::ResponseMsg<M>,
+    ) -> Result<FetchAction<M>, FetcherError<M>> {
+        if self.state != BBoxFetchState::End {
+            // A new fetch is already in progress
+            return Ok(FetchAction::None);
+        }
+
+        if let Some(control) = self.poller.process_response(&mut response) {
+            return Ok(self.process_control(control));
+        }
+
+        // Send the next pending event
+        Ok(self.poller.next_action())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{Engine as _, engine::general_purpose::URL_SAFE};
+    use prosa_utils::msg::simple_string_tvf::SimpleStringTvf;
+
+    use super::*;
+    use crate::control;
+    use crate::mock::{MockServer, fetcher_proc, json_response, service_response};
+
+    /// Credentials of the BBox in the URL, with the password encoded in base64-url
+    fn bbox_credentials() -> String {
+        format!(":{}@", URL_SAFE.encode("secret"))
+    }
+
+    /// Parse a captured BBox API response
+    fn parse_fixture<T>(json: &[u8]) -> Vec<T>
+    where
+        T: DeserializeOwned,
+    {
+        serde_json::from_slice(json).unwrap()
+    }
+
+    /// Merge captured BBox API responses into statistics
+    fn merge_fixtures(stats: &mut BBoxStats, fixtures: &[&[u8]]) {
+        for json in fixtures {
+            for api_resp in parse_fixture::<BBoxApiResponse>(json) {
+                stats.merge(api_resp);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_device() {
+        let device_resp: Vec<BBoxDeviceResponse> =
+            parse_fixture(include_bytes!("../tests/fixtures/bbox/device.json"));
+        let device = &device_resp[0].device;
+        assert_eq!(device.modelname, "F@st5696b");
+        assert_eq!(device.serialnumber, "XQ2210123456789");
+        assert_eq!(device.uptime, Some(1_232_591));
+        assert_eq!(device.numberofboots, Some(12));
+        // Empty versions are ignored
+        assert_eq!(
+            device.get_firmwares(),
+            [
+                ("main", "24.2.18"),
+                ("reco", "23.6.28"),
+                ("running", "24.2.18"),
+                ("bcck", "0.0.9"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_device_missing_identity() {
+        let err = serde_json::from_slice::<Vec<BBoxDeviceResponse>>(include_bytes!(
+            "../tests/fixtures/bbox/device_missing_serial.json"
+        ))
+        .unwrap_err();
+        assert!(err.to_string().contains("serialnumber"), "{err}");
+    }
+
+    #[test]
+    fn parse_cpu_mem() {
+        let mut stats = BBoxStats::default();
+        merge_fixtures(
+            &mut stats,
+            &[
+                include_bytes!("../tests/fixtures/bbox/device_cpu.json"),
+                include_bytes!("../tests/fixtures/bbox/device_mem.json"),
+            ],
+        );
+
+        let cpu = stats.cpu.as_ref().unwrap();
+        assert_eq!(cpu.time.total, 84_736_512);
+        assert_eq!(cpu.time.idle, 78_139_512);
+        assert_eq!(cpu.process.as_ref().unwrap().created, 3_264_514);
+        assert_eq!(cpu.temperature.as_ref().unwrap().main, 58);
+        let previous = BBoxCpuTime {
+            total: 84_736_412,
+            idle: 78_139_487,
+        };
+        assert_eq!(cpu.time.get_busy_percent(&previous), Some(75.0));
+        assert_eq!(previous.get_busy_percent(&cpu.time), None);
+
+        let mem = stats.mem.as_ref().unwrap();
+        assert_eq!(mem.total, 1_018_572);
+        assert_eq!(
+            mem.get_used_percent(),
+            Some((1_018_572 - 412_796 - 186_412) as f64 * 100f64 / 1_018_572f64)
+        );
+    }
+
+    #[test]
+    fn parse_traffic() {
+        let device_resp: Vec<BBoxDeviceResponse> =
+            parse_fixture(include_bytes!("../tests/fixtures/bbox/device.json"));
+        let mut stats = BBoxStats {
+            device: device_resp.into_iter().next().map(|resp| resp.device),
+            ..Default::default()
+        };
+        merge_fixtures(
+            &mut stats,
+            &[
+                include_bytes!("../tests/fixtures/bbox/wan_ip_stats.json"),
+                include_bytes!("../tests/fixtures/bbox/lan_stats.json"),
+            ],
+        );
+
+        let wan = stats.wan.as_ref().unwrap();
+        assert_eq!(wan.rx.bytes, 25_416_321_848);
+        assert_eq!(wan.rx.packetsdiscards, Some(12));
+        assert_eq!(wan.tx.bandwidth, Some(1954));
+        assert_eq!(stats.lan.len(), 2);
+        assert_eq!(stats.lan[0].rx.packetserrors, Some(3));
+
+        let traffic = stats.get_traffic();
+        assert_eq!(traffic.len(), 3);
+        assert_eq!(
+            traffic[0].0,
+            [
+                KeyValue::new("serial", "XQ2210123456789"),
+                KeyValue::new("model", "F@st5696b"),
+                KeyValue::new("type", "wan"),
+            ]
+        );
+        assert_eq!(
+            traffic[2].0[2..],
+            [KeyValue::new("port", "1"), KeyValue::new("type", "lan")]
+        );
+    }
+
+    #[test]
+    fn parse_line() {
+        let mut line = BBoxLine::default();
+        for json in [
+            include_bytes!("../tests/fixtures/bbox/wan_xdsl.json").as_slice(),
+            include_bytes!("../tests/fixtures/bbox/wan_xdsl_stats.json").as_slice(),
+            include_bytes!("../tests/fixtures/bbox/wan_ftth.json").as_slice(),
+        ] {
+            for line_resp in parse_fixture::<BBoxLineResponse>(json) {
+                line.merge(line_resp.wan);
+            }
+        }
+
+        // The xDSL stats are merged into the xDSL line
+        let xdsl = line.xdsl.as_ref().unwrap();
+        assert_eq!(xdsl.state, "Connected");
+        assert_eq!(xdsl.showtime, Some(1_210_356f64));
+        assert_eq!(xdsl.down.bitrates, Some(58936f64));
+        assert_eq!(xdsl.down.noise, Some(62f64));
+        assert_eq!(xdsl.down.attenuation, Some(183f64));
+        let xdsl_stats = xdsl.stats.as_ref().unwrap();
+        assert_eq!(xdsl_stats.local_crc, Some(12f64));
+        assert_eq!(xdsl_stats.remote_fec, Some(3f64));
+
+        let ftth = line.ftth.as_ref().unwrap();
+        assert_eq!(ftth.state, "Up");
+        assert_eq!(ftth.rxpower, Some(-17.9));
+        assert_eq!(ftth.txpower, Some(2.43));
+    }
+
+    #[test]
+    fn parse_wireless_hosts() {
+        let mut stats = BBoxStats::default();
+        assert_eq!(stats.get_wifi_radio_ids(), [24, 5]);
+        for wireless_resp in parse_fixture::<BBoxWirelessResponse>(include_bytes!(
+            "../tests/fixtures/bbox/wireless.json"
+        )) {
+            stats.merge_radios(wireless_resp.wireless);
+        }
+        assert_eq!(stats.get_wifi_radio_ids(), [5, 24]);
+        let radio = &stats.wifi_radios[&5];
+        assert!(radio.enable);
+        assert_eq!(radio.current_channel, Some(36));
+        assert_eq!(radio.htbw, Some(80));
+
+        merge_fixtures(
+            &mut stats,
+            &[include_bytes!("../tests/fixtures/bbox/hosts.json")],
+        );
+        let hosts = stats.get_hosts();
+        assert_eq!(hosts.len(), 2);
+        assert!(hosts[0].active);
+        assert_eq!(hosts[0].wireless.as_ref().unwrap().rssi0, Some(-58));
+        assert!(!hosts[1].active);
+        assert!(hosts[1].wireless.is_none());
+        assert_eq!(
+            stats.get_wifi_stations(),
+            BTreeMap::from([("2.4GHz".to_string(), 0), ("5GHz".to_string(), 1)])
+        );
+    }
+
+    #[test]
+    fn parse_wifi_stats() {
+        let mut stats = BBoxStats::default();
+        for radio_id in [24, 5] {
+            for wireless_resp in parse_fixture::<BBoxWirelessStatsResponse>(include_bytes!(
+                "../tests/fixtures/bbox/wireless_stats.json"
+            )) {
+                stats.merge_wifi_stats(radio_id, wireless_resp);
+            }
+        }
+
+        // Statistics are kept by radio, even if the SSID is shared between the bands
+        assert_eq!(stats.wifi.keys().copied().collect::<Vec<_>>(), [5, 24]);
+        assert_eq!(stats.wifi[&24].rx.bytes, 1_834_523_412);
+        assert_eq!(stats.wifi[&5].tx.packetserrors, Some(2));
+        let bands: Vec<Vec<KeyValue>> = stats
+            .get_traffic()
+            .into_iter()
+            .map(|(labels, _)| labels)
+            .collect();
+        assert_eq!(
+            bands,
+            [
+                [KeyValue::new("band", "5GHz"), KeyValue::new("type", "wifi")],
+                [
+                    KeyValue::new("band", "2.4GHz"),
+                    KeyValue::new("type", "wifi")
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_voip() {
+        let mut stats = BBoxStats::default();
+        merge_fixtures(
+            &mut stats,
+            &[include_bytes!("../tests/fixtures/bbox/voip.json")],
+        );
+        assert_eq!(stats.voip.len(), 1);
+        assert!(stats.voip[0].is_registered());
+        assert_eq!(stats.voip[0].get_state(), 1);
+        assert_eq!(stats.voip[0].uri, "0987654321");
+        assert_eq!(stats.voip[0].message_count, Some(2));
+        for (status, callstate, state) in [
+            ("Up", "Ringing", 2),
+            ("Up", "InCall", 3),
+            ("Up", "Dialing", 4),
+            ("Down", "Idle", 0),
+        ] {
+            let line = BBoxVoipLine {
+                status: status.to_string(),
+                callstate: callstate.to_string(),
+                ..Default::default()
+            };
+            assert_eq!(line.get_state(), state, "{status} {callstate}");
+        }
+
+        for api_resp in parse_fixture::<BBoxApiResponse>(include_bytes!(
+            "../tests/fixtures/bbox/fullcalllog.json"
+        )) {
+            stats.merge_calls(1, api_resp);
+        }
+        let calls = &stats.calls[&1];
+        assert_eq!(
+            calls
+                .iter()
+                .map(|call| (call.id, call.get_type(), call.duree))
+                .collect::<Vec<_>>(),
+            [
+                (52, "missed", 0),
+                (51, "accepted", 124),
+                (50, "outgoing", 37)
+            ]
+        );
+        assert_eq!(calls[1].date, 1_760_511_600);
+    }
+
+    #[test]
+    fn missed_call_events() {
+        let proc = fetcher_proc(
+            "bbox-missed-calls",
+            &format!("https://{}127.0.0.1", bbox_credentials()),
+            Some("event_service: HOME_EVENT"),
+        );
+        let mut adaptor = <FetcherBBoxAdaptor as FetcherAdaptor<SimpleStringTvf>>::new(&proc)
+            .expect("BBox adaptor");
+        let mut stats = BBoxStats::default();
+        merge_fixtures(
+            &mut stats,
+            &[include_bytes!("../tests/fixtures/bbox/voip.json")],
+        );
+        for api_resp in parse_fixture::<BBoxApiResponse>(include_bytes!(
+            "../tests/fixtures/bbox/fullcalllog.json"
+        )) {
+            stats.merge_calls(1, api_resp);
+        }
+
+        // The calls already in the log are counted, but not notified
+        adaptor.detect_calls(&stats.voip, &stats.calls);
+        assert!(matches!(
+            adaptor.poller.next_action::<SimpleStringTvf>(),
+            FetchAction::None
+        ));
+        assert_eq!(adaptor.last_call_ids.get(&1), Some(&52));
+
+        // Only the new missed calls are notified
+        let line_calls = stats.calls.get_mut(&1).unwrap();
+        for (id, call_type, answered, number, date) in [
+            (55, "out", true, "0987654321", 1_760_608_800),
+            (54, "in", true, "0123456789", 1_760_605_200),
+            (53, "in", false, "0611223344", 1_760_601_600),
+        ] {
+            line_calls.insert(
+                0,
+                BBoxCall {
+                    id,
+                    number: number.to_string(),
+                    date,
+                    call_type: call_type.to_string(),
+                    answered,
+                    ..Default::default()
+                },
+            );
+        }
+        adaptor.detect_calls(&stats.voip, &stats.calls);
+        let FetchAction::Srv(service, event) = adaptor.poller.next_action::<SimpleStringTvf>()
+        else {
+            panic!("Expected a missed call event");
+        };
+        assert_eq!(service, "HOME_EVENT");
+        assert_eq!(
+            event.get_string(BBoxEvent::EVENT_TYPE).unwrap().as_ref(),
+            "missed_call"
+        );
+        assert_eq!(
+            event.get_string(BBoxEvent::CALL_LINE).unwrap().as_ref(),
+            "0987654321"
+        );
+        assert_eq!(
+            event.get_string(BBoxEvent::CALL_NUMBER).unwrap().as_ref(),
+            "0611223344"
+        );
+        assert_eq!(
+            event.get_unsigned(BBoxEvent::CALL_DATETIME).unwrap(),
+            1_760_601_600
+        );
+        assert!(matches!(
+            adaptor.poller.next_action::<SimpleStringTvf>(),
+            FetchAction::None
+        ));
+        assert_eq!(adaptor.last_call_ids.get(&1), Some(&55));
+        assert_eq!(adaptor.calls_total.get("missed"), Some(&2));
+        assert_eq!(adaptor.calls_total.get("accepted"), Some(&2));
+        assert_eq!(adaptor.calls_total.get("outgoing"), Some(&2));
+    }
+
+    #[test]
+    fn parse_remote() {
+        let token_resp: Vec<BBoxTokenResponse> =
+            parse_fixture(include_bytes!("../tests/fixtures/bbox/device_token.json"));
+        assert_eq!(
+            token_resp[0].device.token,
+            "5f4dcc3b5aa765d61d8327deb882cf99"
+        );
+
+        let mut stats = BBoxStats::default();
+        merge_fixtures(
+            &mut stats,
+            &[include_bytes!("../tests/fixtures/bbox/remote_admin.json")],
+        );
+        let remote_admin = stats.remote_admin.as_ref().unwrap();
+        assert!(remote_admin.enable);
+        assert_eq!(remote_admin.port, Some(8560));
+        assert!(remote_admin.is_temporary());
+    }
+
+    #[test]
+    fn session_cookie() {
+        assert_eq!(
+            parse_session_cookie("BBOX_ID=abc123; Path=/; HttpOnly"),
+            Some(("abc123".to_string(), None))
+        );
+        assert_eq!(parse_session_cookie("OTHER=abc123; Max-Age=3600"), None);
+
+        let (bbox_id, expiry) =
+            parse_session_cookie("BBOX_ID=abc123; Path=/; Max-Age=3600; HttpOnly").unwrap();
+        assert_eq!(bbox_id, "abc123");
+        let max_age = expiry.unwrap().duration_since(SystemTime::now()).unwrap();
+        assert!(max_age <= Duration::from_secs(3600) && max_age > Duration::from_secs(3590));
+
+        assert_eq!(
+            parse_session_cookie("BBOX_ID=abc123; expires=Wed, 21 Oct 2026 07:28:00 GMT; Path=/"),
+            Some((
+                "abc123".to_string(),
+                Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_792_567_680))
+            ))
+        );
+        assert_eq!(
+            parse_session_cookie("BBOX_ID=abc123; Expires=invalid date"),
+            Some(("abc123".to_string(), None))
+        );
+
+        // `Max-Age` have precedence over `Expires`
+        let (_, expiry) = parse_session_cookie(
+            "BBOX_ID=abc123; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Max-Age=60",
+        )
+        .unwrap();
+        assert!(expiry.unwrap() > SystemTime::now());
+    }
+
+    #[tokio::test]
+    async fn login_rejected() {
+        let server = MockServer::start(|_| {
+            json_response(
+                StatusCode::UNAUTHORIZED,
+                r#"[{"exception":{"domain":"/api/v1/login","code":"401","errors":[{"name":"password","reason":"Invalid password"}]}}]"#,
+            )
+        })
+        .await;
+        let proc = fetcher_proc(
+            "bbox-login-rejected",
+            &server.url(&bbox_credentials()),
+            Some("max_login_attempts: 3\nlogin_retry_delay: 10"),
+        );
+        let mut adaptor = <FetcherBBoxAdaptor as FetcherAdaptor<SimpleStringTvf>>::new(&proc)
+            .expect("BBox adaptor");
+
+        for (login_attempts, retry_delay) in [(1, 10), (2, 20)] {
+            let before_login = Instant::now();
+            let action = FetcherAdaptor::<SimpleStringTvf>::fetch(&mut adaptor).unwrap();
+            let action = server.run(&mut adaptor, action).await.unwrap();
+            assert!(matches!(action, FetchAction::None));
+            assert_eq!(adaptor.login_attempts, login_attempts);
+
+            // The retry delay is doubled after each failure
+            let login_retry = adaptor.login_retry.unwrap();
+            assert!(login_retry >= before_login + Duration::from_secs(retry_delay));
+            assert!(login_retry <= Instant::now() + Duration::from_secs(retry_delay));
+
+            // No login is tried before the retry delay
+            let nb_requests = server.requests().len();
+            assert!(matches!(
+                FetcherAdaptor::<SimpleStringTvf>::fetch(&mut adaptor),
+                Ok(FetchAction::None)
+            ));
+            assert_eq!(server.requests().len(), nb_requests);
+            adaptor.login_retry = Some(Instant::now());
+        }
+
+        // The last attempts return an authentication error, and keep the capped delay
+        for login_attempts in [3, 4] {
+            let before_login = Instant::now();
+            let action = FetcherAdaptor::<SimpleStringTvf>::fetch(&mut adaptor).unwrap();
+            match server.run(&mut adaptor, action).await {
+                Err(FetcherError::Io(e)) => {
+                    assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+                    assert!(
+                        e.to_string()
+                            .contains(&format!("failed {login_attempts} times")),
+                        "{e}"
+                    );
+                }
+                Err(e) => panic!("Unexpected error {e:?}"),
+                Ok(action) => panic!("Unexpected action {action:?}"),
+            }
+
+            let login_retry = adaptor.login_retry.unwrap();
+            assert!(login_retry >= before_login + Duration::from_secs(40));
+            assert!(login_retry <= Instant::now() + Duration::from_secs(40));
+
+            // No login is tried before the retry delay
+            let nb_requests = server.requests().len();
+            assert!(matches!(
+                FetcherAdaptor::<SimpleStringTvf>::fetch(&mut adaptor),
+                Ok(FetchAction::None)
+            ));
+            assert_eq!(server.requests().len(), nb_requests);
+            adaptor.login_retry = Some(Instant::now());
+        }
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 4);
+        for request in requests {
+            assert_eq!(request.method, Method::POST);
+            assert_eq!(request.path(), "/api/v1/login");
+            assert_eq!(request.body, "password=secret");
+        }
+    }
+
+    /// Mock of the BBox API with a session cookie and a `btoken` renewed at each login.
+    /// The first token expires once the CPU endpoint is called.
+    async fn remote_bbox() -> MockServer {
+        let mut nb_logins = 0;
+        MockServer::start(move |request| {
+            let btoken = request.query_param("btoken");
+            match request.path() {
+                "/api/v1/login" => {
+                    nb_logins += 1;
+                    let mut response = json_response(StatusCode::OK, "");
+                    response.headers_mut().insert(
+                        http::header::SET_COOKIE,
+                        format!("BBOX_ID=session-{nb_logins}; Path=/; Max-Age=3600; HttpOnly")
+                            .parse()
+                            .unwrap(),
+                    );
+                    response
+                }
+                "/api/v1/device/token" => json_response(
+                    StatusCode::OK,
+                    &format!(r#"[{{"device":{{"token":"token-{nb_logins}"}}}}]"#),
+                ),
+                _ if btoken.is_none_or(|btoken| btoken != format!("token-{nb_logins}")) => {
+                    json_response(StatusCode::UNAUTHORIZED, "")
+                }
+                "/api/v1/device/cpu" if btoken == Some("token-1") => {
+                    json_response(StatusCode::UNAUTHORIZED, "")
+                }
+                path => {
+                    let json: &[u8] = match path {
+                        "/api/v1/remote/admin" if request.method == Method::GET => {
+                            include_bytes!("../tests/fixtures/bbox/remote_admin.json")
+                        }
+                        "/api/v1/remote/admin" => b"",
+                        "/api/v1/parentalcontrol" => b"",
+                        "/api/v1/device" => include_bytes!("../tests/fixtures/bbox/device.json"),
+                        "/api/v1/device/cpu" => {
+                            include_bytes!("../tests/fixtures/bbox/device_cpu.json")
+                        }
+                        "/api/v1/device/mem" => {
+                            include_bytes!("../tests/fixtures/bbox/device_mem.json")
+                        }
+                        "/api/v1/wan/ip/stats" => {
+                            include_bytes!("../tests/fixtures/bbox/wan_ip_stats.json")
+                        }
+                        "/api/v1/wan/ftth" => {
+                            include_bytes!("../tests/fixtures/bbox/wan_ftth.json")
+                        }
+                        "/api/v1/lan/stats" => {
+                            include_bytes!("../tests/fixtures/bbox/lan_stats.json")
+                        }
+                        "/api/v1/wireless" => {
+                            include_bytes!("../tests/fixtures/bbox/wireless.json")
+                        }
+                        "/api/v1/wireless/24/stats" | "/api/v1/wireless/5/stats" => {
+                            include_bytes!("../tests/fixtures/bbox/wireless_stats.json")
+                        }
+                        "/api/v1/hosts" => include_bytes!("../tests/fixtures/bbox/hosts.json"),
+                        "/api/v1/voip" => include_bytes!("../tests/fixtures/bbox/voip.json"),
+                        "/api/v1/voip/fullcalllog/1" => {
+                            include_bytes!("../tests/fixtures/bbox/fullcalllog.json")
+                        }
+                        _ => return json_response(StatusCode::NOT_FOUND, ""),
+                    };
+                    json_response(StatusCode::OK, std::str::from_utf8(json).unwrap())
+                }
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn remote_token() {
+        let server = remote_bbox().await;
+        let proc = fetcher_proc(
+            "bbox-remote-token",
+            &server.url(&bbox_credentials()),
+            Some("remote:\n  keep_enabled: true"),
+        );
+        let mut adaptor = <FetcherBBoxAdaptor as FetcherAdaptor<SimpleStringTvf>>::new(&proc)
+            .expect("BBox adaptor");
+        let watch_bbox = adaptor.meter_bbox.subscribe();
+
+        let action = FetcherAdaptor::<SimpleStringTvf>::fetch(&mut adaptor).unwrap();
+        let action = server.run(&mut adaptor, action).await.unwrap();
+        assert!(matches!(action, FetchAction::None));
+        assert_eq!(adaptor.state, BBoxFetchState::End);
+        assert_eq!(adaptor.btoken.as_deref(), Some("token-2"));
+
+        let requests = server.requests();
+        let calls: Vec<(&str, Option<&str>)> = requests
+            .iter()
+            .map(|request| (request.path(), request.query_param("btoken")))
+            .collect();
+        assert_eq!(
+            calls[..11],
+            [
+                ("/api/v1/login", None),
+                ("/api/v1/device/token", None),
+                ("/api/v1/remote/admin", Some("token-1")),
+                ("/api/v1/remote/admin", Some("token-1")),
+                ("/api/v1/device", Some("token-1")),
+                // The token expires, so a new session and token are retrieved
+                ("/api/v1/device/cpu", Some("token-1")),
+                ("/api/v1/login", None),
+                ("/api/v1/device/token", None),
+                ("/api/v1/remote/admin", Some("token-2")),
+                ("/api/v1/remote/admin", Some("token-2")),
+                ("/api/v1/device", Some("token-2")),
+            ]
+        );
+        // The temporary remote access is renewed
+        assert_eq!(requests[3].method, Method::PUT);
+        assert_eq!(requests[3].body, "enable=1");
+        assert_eq!(
+            calls.last(),
+            Some(&("/api/v1/voip/fullcalllog/1", Some("token-2")))
+        );
+        for request in &requests[11..] {
+            assert_eq!(request.query_param("btoken"), Some("token-2"));
+            assert_eq!(request.header("cookie"), Some("BBOX_ID=session-2"));
+        }
+
+        // Every metric have been collected with the renewed token
+        let stats = watch_bbox.borrow();
+        assert_eq!(stats.device.as_ref().unwrap().modelname, "F@st5696b");
+        assert!(stats.cpu.is_some() && stats.mem.is_some() && stats.wan.is_some());
+        assert_eq!(stats.wifi.len(), 2);
+        assert_eq!(stats.calls_total.values().sum::<u64>(), 3);
+    }
+
+    #[test]
+    fn control_parental_host() {
+        let mut msg = SimpleStringTvf::default();
+        msg.put_string(BBoxControl::PARENTAL_HOST_MAC, "AA:BB:CC:DD:EE:FF");
+        msg.put_unsigned(BBoxControl::PARENTAL_HOST_ENABLED, 1);
+        let control = BBoxControl::parse("parental_host", &msg).unwrap();
+        let config = BBoxControlConfig {
+            parental: false,
+            parental_hosts: vec!["aa:bb:cc:dd:ee:ff".to_string()],
+        };
+        assert!(control.is_allowed(&config));
+
+        // Enabling the rule of the host applies the parental control to it
+        let (method, uri, body) = control.call();
+        assert_eq!(method, Method::PUT);
+        assert_eq!(uri.path(), "/api/v1/parentalcontrol/hosts");
+        assert_eq!(body, "macaddress=aa:bb:cc:dd:ee:ff&enable=1");
+
+        // Only a MAC address can be sent in the form body
+        for mac in [
+            "aa:bb:cc:dd:ee:ff&enable=0",
+            "aa:bb:cc:dd:ee",
+            "aa:bb:cc:dd:ee:ff:00",
+            "aa:bb:cc:dd:ee:fg",
+            "aabb:cc:dd:ee:ff:",
+        ] {
+            let mut msg = SimpleStringTvf::default();
+            msg.put_string(BBoxControl::PARENTAL_HOST_MAC, mac);
+            msg.put_unsigned(BBoxControl::PARENTAL_HOST_ENABLED, 1);
+            assert!(BBoxControl::parse("parental_host", &msg).is_err(), "{mac}");
+        }
+    }
+
+    #[tokio::test]
+    async fn control_parental() {
+        let server = remote_bbox().await;
+        let proc = fetcher_proc(
+            "bbox-control-parental",
+            &server.url(&bbox_credentials()),
+            Some("remote: {}\ncontrol:\n  service: BBOX_CONTROL\n  parental: true"),
+        );
+        let mut adaptor = <FetcherBBoxAdaptor as FetcherAdaptor<SimpleStringTvf>>::new(&proc)
+            .expect("BBox adaptor");
+
+        // The control service is polled at the end of the fetch
+        let action = FetcherAdaptor::<SimpleStringTvf>::fetch(&mut adaptor).unwrap();
+        let FetchAction::Srv(service, msg) = server.run(&mut adaptor, action).await.unwrap() else {
+            panic!("Expected a control poll");
+        };
+        assert_eq!(service, "BBOX_CONTROL");
+        assert_eq!(msg.get_string(control::ACTION).unwrap().as_ref(), "poll");
+
+        // The requested action is done with a fresh token, and its result is sent back
+        let nb_requests = server.requests().len();
+        let mut msg = SimpleStringTvf::default();
+        msg.put_string(control::ACTION, "parental");
+        msg.put_unsigned(BBoxControl::PARENTAL_ENABLED, 0);
+        let action = adaptor
+            .process_service_response(service_response("BBOX_CONTROL", msg))
+            .unwrap();
+        let FetchAction::Srv(service, msg) = server.run(&mut adaptor, action).await.unwrap() else {
+            panic!("Expected a control result");
+        };
+        assert_eq!(service, "BBOX_CONTROL");
+        assert_eq!(msg.get_string(control::ACTION).unwrap().as_ref(), "result");
+        assert_eq!(
+            msg.get_string(control::RESULT_ACTION).unwrap().as_ref(),
+            "parental"
+        );
+        assert_eq!(msg.get_unsigned(control::RESULT_SUCCESS).unwrap(), 1);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), nb_requests + 2);
+        assert_eq!(requests[nb_requests].path(), "/api/v1/device/token");
+        let control_request = &requests[nb_requests + 1];
+        assert_eq!(control_request.method, Method::PUT);
+        assert_eq!(control_request.path(), "/api/v1/parentalcontrol");
+        assert_eq!(control_request.body, "enable=0");
+
+        // An action not allowed by the configuration is rejected without calling the BBox
+        let mut msg = SimpleStringTvf::default();
+        msg.put_string(control::ACTION, "parental_host");
+        msg.put_string(BBoxControl::PARENTAL_HOST_MAC, "aa:bb:cc:dd:ee:ff");
+        msg.put_unsigned(BBoxControl::PARENTAL_HOST_ENABLED, 1);
+        let action = adaptor
+            .process_service_response(service_response("BBOX_CONTROL", msg))
+            .unwrap();
+        let FetchAction::Srv(_, msg) = action else {
+            panic!("Expected a control result");
+        };
+        assert_eq!(
+            msg.get_string(control::RESULT_ACTION).unwrap().as_ref(),
+            "parental_host"
+        );
+        assert_eq!(msg.get_unsigned(control::RESULT_SUCCESS).unwrap(), 0);
+        assert_eq!(server.requests().len(), nb_requests + 2);
+
+        // No more pending action
+        let action = adaptor
+            .process_service_response(service_response("BBOX_CONTROL", SimpleStringTvf::default()))
+            .unwrap();
+        assert!(matches!(action, FetchAction::None));
+    }
 }
@@ -0,0 +1,207 @@
+//! Events and control actions shared by the adaptors
+//!
+//! As a fetcher processor can't receive requests, the adaptors poll their control service for pending actions.
+//! Events and control results are sent at the end of a fetch, when the adaptor has nothing else to do.
+
+use std::{collections::VecDeque, convert::Infallible};
+
+use prosa::core::msg::{Msg as _, ResponseMsg, Tvf};
+use prosa_fetcher::proc::FetchAction;
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+
+/// TVF field of the control message type (`poll` or `result` to the control service, action name in its responses)
+pub const ACTION: usize = 1;
+/// TVF field of the action name in a `result` message
+pub const RESULT_ACTION: usize = 2;
+/// TVF field of the action result in a `result` message (1 for success, 0 for failure)
+pub const RESULT_SUCCESS: usize = 3;
+
+/// Event detected by an adaptor, sent to its `event_service` as a TVF message
+pub trait Event {
+    /// Method to convert the event into a TVF message
+    fn to_tvf<M>(&self) -> M
+    where
+        M: Tvf + std::default::Default;
+}
+
+/// Adaptor without events
+impl Event for Infallible {
+    fn to_tvf<M>(&self) -> M
+    where
+        M: Tvf + std::default::Default,
+    {
+        match *self {}
+    }
+}
+
+/// Control action requested to an adaptor by its control service
+pub trait ControlAction: Sized {
+    /// Configuration of the allowed actions
+    type Config;
+
+    /// Parse the action from the control service response
+    fn parse<M>(action: &str, msg: &M) -> Result<Self, String>
+    where
+        M: Tvf;
+
+    /// Getter of the action name
+    fn get_name(&self) -> &'static str;
+
+    /// Method to know if the action is allowed by the configuration
+    fn is_allowed(&self, config: &Self::Config) -> bool;
+}
+
+/// Configuration of the control actions of an adaptor, every action is disabled by default
+#[derive(Debug, Clone, Deserialize)]
+pub struct ControlConfig<C> {
+    /// Service polled for pending control actions, and notified of their results
+    pub service: String,
+    /// Allowed actions
+    #[serde(flatten)]
+    pub actions: C,
+}
+
+/// Queue of the events and control results of an adaptor, with the polling of its control service
+#[derive(Debug)]
+pub struct ControlPoller<E, C>
+where
+    C: ControlAction,
+{
+    /// Name of the adaptor for the logs
+    name: &'static str,
+    event_service: Option<String>,
+    control: Option<ControlConfig<C::Config>>,
+    events: VecDeque<E>,
+    /// Results of the control actions (name and success)
+    results: VecDeque<(&'static str, bool)>,
+    /// The control service has been polled, and will answer with the next pending action
+    polled: bool,
+}
+
+impl<E, C> ControlPoller<E, C>
+where
+    E: Event,
+    C: ControlAction,
+{
+    /// Create a poller sending the events to the `event_service`, and polling the control service if configured
+    pub fn new(
+        name: &'static str,
+        event_service: Option<String>,
+        control: Option<ControlConfig<C::Config>>,
+    ) -> ControlPoller<E, C> {
+        ControlPoller {
+            name,
+            event_service,
+            control,
+            events: VecDeque::new(),
+            results: VecDeque::new(),
+            polled: false,
+        }
+    }
+
+    /// Method to know if control actions are configured
+    pub fn has_control(&self) -> bool {
+        self.control.is_some()
+    }
+
+    /// Method to queue an event (dropped if there is no `event_service`)
+    pub fn push_event(&mut self, event: E) {
+        if self.event_service.is_some() {
+            self.events.push_back(event);
+        }
+    }
+
+    /// Method to queue the result of a control action for the control service
+    pub fn push_result(&mut self, control: &C, success: bool) {
+        if success {
+            info!("{} control action `{}` done", self.name, control.get_name());
+        }
+        self.results.push_back((control.get_name(), success));
+    }
+
+    /// Method to poll again the control service (at each fetch)
+    pub fn reset(&mut self) {
+        self.polled = false;
+    }
+
+    /// Method to get the next message to send once the adaptor has nothing else to do.
+    /// Pending events are sent first, then the control results. The control service is then polled once.
+    pub fn next_action<M>(&mut self) -> FetchAction<M>
+    where
+        M: Tvf + std::default::Default + std::marker::Send,
+    {
+        if let Some(event_service) = &self.event_service
+            && let Some(event) = self.events.pop_front()
+        {
+            return FetchAction::Srv(event_service.clone(), event.to_tvf());
+        }
+
+        let Some(control) = &self.control else {
+            return FetchAction::None;
+        };
+
+        if let Some((action, success)) = self.results.pop_front() {
+            // The control service answers the result with the next pending action
+            self.polled = true;
+            let mut msg = M::default();
+            msg.put_string(ACTION, "result");
+            msg.put_string(RESULT_ACTION, action);
+            msg.put_unsigned(RESULT_SUCCESS, success as u64);
+            FetchAction::Srv(control.service.clone(), msg)
+        } else if !self.polled {
+            // Ask the control service for a pending action
+            self.polled = true;
+            let mut msg = M::default();
+            msg.put_string(ACTION, "poll");
+            FetchAction::Srv(control.service.clone(), msg)
+        } else {
+            FetchAction::None
+        }
+    }
+
+    /// Method to process a service response, and get the control action to do if the control service asked for an allowed one.
+    /// A rejected action is answered with a failed result.
+    pub fn process_response<M>(&mut self, response: &mut ResponseMsg<M>) -> Option<C>
+    where
+        M: Tvf + std::clone::Clone + std::marker::Send + std::marker::Sync,
+    {
+        let control_config = self
+            .control
+            .as_ref()
+            .filter(|control_config| response.get_service() == &control_config.service)?;
+        let data = response.take_data()?;
+        if !data.contains(ACTION) {
+            debug!("No pending {} control action", self.name);
+            return None;
+        }
+
+        let control = match data.get_string(ACTION) {
+            Ok(action) => C::parse(&action, &data),
+            Err(e) => Err(format!("invalid action field: {e}")),
+        };
+        match control {
+            Ok(control) if control.is_allowed(&control_config.actions) => {
+                info!(
+                    "{} control action `{}` requested",
+                    self.name,
+                    control.get_name()
+                );
+                Some(control)
+            }
+            Ok(control) => {
+                warn!(
+                    "{} control action `{}` is not allowed by the configuration",
+                    self.name,
+                    control.get_name()
+                );
+                self.results.push_back((control.get_name(), false));
+                None
+            }
+            Err(e) => {
+                warn!("Invalid {} control action: {e}", self.name);
+                None
+            }
+        }
+    }
+}
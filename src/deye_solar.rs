@@ -1,19 +1,94 @@
 //! Fetcher adaptor for [Deye](https://deye.com/fr/product/sun-m60-80-100g4-eu-q0/) solar inverter
 
-use std::convert::Infallible;
+use std::{convert::Infallible, io, time::Duration};
 
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
 use http::{Method, Request, Response, StatusCode, Uri, request};
 use http_body_util::{BodyExt as _, combinators::BoxBody};
 use hyper::body::Incoming;
 use opentelemetry::KeyValue;
-use prosa::core::{adaptor::Adaptor, proc::ProcConfig as _};
+use prosa::core::{
+    adaptor::Adaptor,
+    msg::{RequestMsg, Tvf},
+    proc::{ProcConfig as _, ProcSettings as _},
+};
 use prosa_fetcher::{
     adaptor::FetcherAdaptor,
     proc::{FetchAction, FetcherError, FetcherProc},
 };
-use tokio::sync::watch;
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncReadExt as _, AsyncWriteExt as _},
+    net::TcpStream,
+    sync::{mpsc, watch},
+};
 use tracing::{debug, warn};
 
+use crate::control::{ControlAction, ControlConfig, ControlPoller};
+
+/// DC data of a PV input of the Deye inverter
+#[derive(Debug, Default)]
+struct DeyeSolarPv {
+    /// Voltage in V
+    voltage: f64,
+    /// Current in A
+    current: f64,
+    /// Daily yield in kWh
+    yield_today: f64,
+    /// Total yield in kWh
+    total_yield: f64,
+}
+
+impl DeyeSolarPv {
+    /// Getter of the DC power (W) of the PV input
+    fn get_power(&self) -> f64 {
+        self.voltage * self.current
+    }
+}
+
+/// Getter of the running state name of the Deye inverter
+fn get_state_name(state: u16) -> &'static str {
+    match state {
+        0 => "standby",
+        1 => "self-check",
+        2 => "normal",
+        3 => "alarm",
+        4 => "fault",
+        _ => "unknown",
+    }
+}
+
+/// Getter of a readable label for a Deye fault code (`F{code}` if the code is unknown)
+fn get_fault_name(code: u16) -> String {
+    match code {
+        1 => "DC inversed".to_string(),
+        7 => "DC start failure".to_string(),
+        13 => "Working mode change".to_string(),
+        15 => "AC over current (software)".to_string(),
+        16 => "GFCI failure".to_string(),
+        18 => "AC over current (hardware)".to_string(),
+        20 => "DC over current".to_string(),
+        22 => "Emergency stop".to_string(),
+        23 => "AC leakage current".to_string(),
+        24 => "DC insulation failure".to_string(),
+        26 => "DC busbar unbalanced".to_string(),
+        35 => "No AC grid".to_string(),
+        41 => "AC line over voltage".to_string(),
+        42 => "AC line low voltage".to_string(),
+        43 => "AC line V,W over voltage".to_string(),
+        44 => "AC line V,W low voltage".to_string(),
+        45 => "AC line U,V over voltage".to_string(),
+        46 => "AC line U,V low voltage".to_string(),
+        47 => "AC over frequency".to_string(),
+        48 => "AC under frequency".to_string(),
+        55 => "DC busbar voltage too high".to_string(),
+        56 => "DC busbar voltage too low".to_string(),
+        63 => "ARC fault".to_string(),
+        64 => "Heat sink high temperature".to_string(),
+        code => format!("F{code:02}"),
+    }
+}
+
 #[derive(Debug, Default)]
 struct DeyeSolarData {
     serial_number: String,
@@ -22,6 +97,20 @@ struct DeyeSolarData {
     total_yield: f64,
     wireless_router_ssid: String,
     wireless_signal_quality: u8,
+    /// PV inputs (only available through Modbus)
+    pv: Vec<DeyeSolarPv>,
+    /// Grid voltage in V
+    grid_voltage: Option<f64>,
+    /// Grid current in A
+    grid_current: Option<f64>,
+    /// Grid frequency in Hz
+    grid_frequency: Option<f64>,
+    /// Radiator temperature in °C
+    radiator_temperature: Option<f64>,
+    /// Running state of the inverter
+    running_state: Option<u16>,
+    /// Active fault codes
+    faults: Vec<u16>,
 }
 
 impl DeyeSolarData {
@@ -82,21 +171,793 @@ impl TryFrom<String> for DeyeSolarData {
                 .to_string(),
             wireless_signal_quality: wireless_signal_quality
                 .ok_or("Missing wireless signal quality [cover_sta_rssi]")?,
+            ..Default::default()
+        })
+    }
+}
+
+/// Holding registers of the Deye inverter, read through Modbus
+#[derive(Debug, Default, Clone)]
+struct DeyeSolarRegisters {
+    start: u16,
+    values: Vec<u16>,
+}
+
+impl DeyeSolarRegisters {
+    /// First register read from the inverter
+    const START: u16 = 0x0003;
+    /// Number of registers read from the inverter (up to the last PV input register)
+    const COUNT: u16 = 0x0074 - Self::START + 1;
+
+    /// Serial number of the inverter (ASCII, 5 registers)
+    const SERIAL_NUMBER: u16 = 0x0003;
+    /// Daily production (0.1 kWh)
+    const YIELD_TODAY: u16 = 0x003C;
+    /// Total production (0.1 kWh, 2 registers low word first)
+    const TOTAL_YIELD: u16 = 0x003F;
+    /// AC output power (0.1 W, 2 registers low word first)
+    const CURRENT_POWER: u16 = 0x0056;
+    /// Daily production of the PV inputs (0.1 kWh, 1 register per input)
+    const PV_YIELD_TODAY: u16 = 0x0041;
+    /// Total production of the PV inputs (0.1 kWh, 2 registers low word first per input)
+    const PV_TOTAL_YIELD: [u16; 4] = [0x0045, 0x0047, 0x004A, 0x004D];
+    /// Voltage (0.1 V) and current (0.1 A) of the PV inputs (2 registers per input)
+    const PV_VOLTAGE_CURRENT: u16 = 0x006D;
+    /// Running state (0: standby, 1: self-check, 2: normal, 3: alarm, 4: fault)
+    const RUNNING_STATE: u16 = 0x003B;
+    /// Grid voltage (0.1 V)
+    const GRID_VOLTAGE: u16 = 0x0049;
+    /// Grid current (0.1 A)
+    const GRID_CURRENT: u16 = 0x004C;
+    /// Grid frequency (0.01 Hz)
+    const GRID_FREQUENCY: u16 = 0x004F;
+    /// Radiator temperature (0.01 °C with an offset of 10 °C)
+    const RADIATOR_TEMPERATURE: u16 = 0x005A;
+    /// Fault bits (4 registers, the bit `n` is the fault code `n + 1`)
+    const FAULTS: u16 = 0x0065;
+    /// Active power regulation (% of the rated power)
+    const POWER_LIMIT: u16 = 0x0028;
+    /// Power switch (1: on, 2: off)
+    const POWER_SWITCH: u16 = 0x002B;
+
+    /// Getter of a register value
+    fn get(&self, register: u16) -> Option<u16> {
+        register
+            .checked_sub(self.start)
+            .and_then(|index| self.values.get(index as usize))
+            .copied()
+    }
+
+    /// Getter of a 32 bits value stored on two registers (low word first)
+    fn get_u32(&self, register: u16) -> Option<u32> {
+        Some(self.get(register)? as u32 | (self.get(register + 1)? as u32) << 16)
+    }
+
+    /// Getter of the PV input data from its index (starting from 0)
+    fn get_pv(&self, index: u8) -> Option<DeyeSolarPv> {
+        let index = index as u16;
+        Some(DeyeSolarPv {
+            voltage: self.get(Self::PV_VOLTAGE_CURRENT + index * 2)? as f64 / 10f64,
+            current: self.get(Self::PV_VOLTAGE_CURRENT + index * 2 + 1)? as f64 / 10f64,
+            yield_today: self.get(Self::PV_YIELD_TODAY + index)? as f64 / 10f64,
+            total_yield: self.get_u32(*Self::PV_TOTAL_YIELD.get(index as usize)?)? as f64 / 10f64,
+        })
+    }
+
+    /// Getter of the active fault codes
+    fn get_faults(&self) -> Vec<u16> {
+        let mut faults = Vec::new();
+        for word in 0..4u16 {
+            if let Some(bits) = self.get(Self::FAULTS + word) {
+                for bit in 0..16u16 {
+                    if bits & (1 << bit) != 0 {
+                        faults.push(word * 16 + bit + 1);
+                    }
+                }
+            }
+        }
+        faults
+    }
+
+    /// Getter of an ASCII string stored on several registers
+    fn get_string(&self, register: u16, count: u16) -> Option<String> {
+        let mut value = String::with_capacity(count as usize * 2);
+        for reg in register..register + count {
+            for c in self.get(reg)?.to_be_bytes() {
+                if c.is_ascii_graphic() {
+                    value.push(c as char);
+                }
+            }
+        }
+        Some(value)
+    }
+}
+
+impl DeyeSolarData {
+    /// Method to create `DeyeSolarData` from the inverter registers, with the number of PV inputs of the inverter
+    fn from_registers(registers: &DeyeSolarRegisters, pv_inputs: u8) -> Result<Self, &'static str> {
+        Ok(DeyeSolarData {
+            serial_number: registers
+                .get_string(DeyeSolarRegisters::SERIAL_NUMBER, 5)
+                .filter(|sn| !sn.is_empty())
+                .ok_or("Missing serial number register")?,
+            current_power: registers
+                .get_u32(DeyeSolarRegisters::CURRENT_POWER)
+                .ok_or("Missing current power registers")? as u64
+                / 10,
+            yield_today: registers
+                .get(DeyeSolarRegisters::YIELD_TODAY)
+                .ok_or("Missing yield power today register")? as f64
+                / 10f64,
+            total_yield: registers
+                .get_u32(DeyeSolarRegisters::TOTAL_YIELD)
+                .ok_or("Missing total yield power registers")? as f64
+                / 10f64,
+            pv: (0..pv_inputs)
+                .map(|index| registers.get_pv(index).ok_or("Missing PV input registers"))
+                .collect::<Result<Vec<DeyeSolarPv>, &'static str>>()?,
+            grid_voltage: registers
+                .get(DeyeSolarRegisters::GRID_VOLTAGE)
+                .map(|v| v as f64 / 10f64),
+            grid_current: registers
+                .get(DeyeSolarRegisters::GRID_CURRENT)
+                .map(|a| a as f64 / 10f64),
+            grid_frequency: registers
+                .get(DeyeSolarRegisters::GRID_FREQUENCY)
+                .map(|f| f as f64 / 100f64),
+            radiator_temperature: registers
+                .get(DeyeSolarRegisters::RADIATOR_TEMPERATURE)
+                .map(|t| (t as f64 - 1000f64) / 100f64),
+            running_state: registers.get(DeyeSolarRegisters::RUNNING_STATE),
+            faults: registers.get_faults(),
+            ..Default::default()
+        })
+    }
+}
+
+/// Client of the Solarman V5 protocol, used by the Deye data logger to wrap Modbus RTU frames over TCP
+#[derive(Debug, Clone)]
+struct DeyeSolarmanClient {
+    config: DeyeSolarmanConfig,
+    sequence: u8,
+}
+
+impl DeyeSolarmanClient {
+    const FRAME_START: u8 = 0xA5;
+    const FRAME_END: u8 = 0x15;
+    const CONTROL_REQUEST: u16 = 0x4510;
+    const CONTROL_RESPONSE: u16 = 0x1510;
+    /// Size of the V5 header (start, length, control code, sequence and logger serial)
+    const HEADER_LEN: usize = 11;
+    /// Size of the V5 request payload before the Modbus frame
+    const REQUEST_PAYLOAD_LEN: usize = 15;
+    /// Size of the V5 response payload before the Modbus frame
+    const RESPONSE_PAYLOAD_LEN: usize = 14;
+    const MODBUS_READ_HOLDING_REGISTERS: u8 = 0x03;
+    const MODBUS_WRITE_MULTIPLE_REGISTERS: u8 = 0x10;
+    /// Service of the internal response waking the adaptor once a control action is done
+    const WAKE_SERVICE: &'static str = "deye_solarman";
+
+    /// Spawn a task that process the requests to the data logger one after the other.
+    /// The inverter data are published to the metrics as soon as they are read,
+    /// and the adaptor is woken up once a control action is done to reply to the control service.
+    fn spawn<W>(
+        mut self,
+        meter_solar: watch::Sender<DeyeSolarData>,
+        wake: W,
+    ) -> (
+        mpsc::UnboundedSender<DeyeSolarmanRequest>,
+        mpsc::UnboundedReceiver<DeyeSolarmanResponse>,
+    )
+    where
+        W: Fn() + Send + 'static,
+    {
+        let (request_tx, mut request_rx) = mpsc::unbounded_channel();
+        let (response_tx, response_rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            // Serial number of the published data, to reset the metrics
+            let mut serial_number = None;
+            while let Some(request) = request_rx.recv().await {
+                self.sequence = self.sequence.wrapping_add(1);
+                let response = match request {
+                    DeyeSolarmanRequest::Read => {
+                        DeyeSolarmanResponse::Read(self.read_data().await.map(|solar_data| {
+                            serial_number = Some(solar_data.serial_number.clone());
+                            debug!("solar_data: {solar_data:?}");
+                            let _ = meter_solar.send(solar_data);
+                        }))
+                    }
+                    DeyeSolarmanRequest::Control(control) => {
+                        let result = self.control(&control).await;
+                        DeyeSolarmanResponse::Control(control, result)
+                    }
+                    DeyeSolarmanRequest::Reset => {
+                        if let Some(serial_number) = serial_number.take() {
+                            // Send empty data to reset metrics
+                            let _ = meter_solar.send(DeyeSolarData::new(serial_number));
+                        }
+                        continue;
+                    }
+                };
+
+                let is_control = matches!(response, DeyeSolarmanResponse::Control(..));
+                if response_tx.send(response).is_err() {
+                    // The adaptor is stopped
+                    break;
+                } else if is_control {
+                    wake();
+                }
+            }
+        });
+
+        (request_tx, response_rx)
+    }
+
+    /// Compute the Modbus RTU CRC of a frame
+    fn modbus_crc(frame: &[u8]) -> u16 {
+        let mut crc = 0xFFFFu16;
+        for byte in frame {
+            crc ^= *byte as u16;
+            for _ in 0..8 {
+                if crc & 0x0001 != 0 {
+                    crc = (crc >> 1) ^ 0xA001;
+                } else {
+                    crc >>= 1;
+                }
+            }
+        }
+        crc
+    }
+
+    /// Compute the V5 checksum of a frame (without its start byte)
+    fn checksum(frame: &[u8]) -> u8 {
+        frame.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+    }
+
+    /// Wrap a Modbus RTU frame into a V5 request frame
+    fn encode(&self, modbus_frame: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(
+            Self::HEADER_LEN + Self::REQUEST_PAYLOAD_LEN + modbus_frame.len() + 2,
+        );
+        frame.push(Self::FRAME_START);
+        frame.extend(((Self::REQUEST_PAYLOAD_LEN + modbus_frame.len()) as u16).to_le_bytes());
+        frame.extend(Self::CONTROL_REQUEST.to_le_bytes());
+        frame.extend([self.sequence, 0x00]);
+        frame.extend(self.config.logger_serial.to_le_bytes());
+        // Frame type (inverter), sensor type, total working time, power on time and offset time
+        frame.push(0x02);
+        frame.extend([0u8; Self::REQUEST_PAYLOAD_LEN - 1]);
+        frame.extend_from_slice(modbus_frame);
+        frame.push(Self::checksum(&frame[1..]));
+        frame.push(Self::FRAME_END);
+        frame
+    }
+
+    /// Read a V5 response frame, and return its Modbus RTU frame
+    async fn read_response(stream: &mut TcpStream) -> io::Result<Vec<u8>> {
+        loop {
+            let mut header = [0u8; Self::HEADER_LEN];
+            stream.read_exact(&mut header).await?;
+            if header[0] != Self::FRAME_START {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid Solarman frame start {:#04x}", header[0]),
+                ));
+            }
+
+            let payload_len = u16::from_le_bytes([header[1], header[2]]) as usize;
+            let mut frame = Vec::with_capacity(Self::HEADER_LEN + payload_len + 2);
+            frame.extend_from_slice(&header);
+            frame.resize(Self::HEADER_LEN + payload_len + 2, 0);
+            stream.read_exact(&mut frame[Self::HEADER_LEN..]).await?;
+
+            let checksum_index = frame.len() - 2;
+            if frame[checksum_index + 1] != Self::FRAME_END
+                || frame[checksum_index] != Self::checksum(&frame[1..checksum_index])
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Invalid Solarman frame checksum",
+                ));
+            }
+
+            if u16::from_le_bytes([header[3], header[4]]) != Self::CONTROL_RESPONSE {
+                // Heartbeat or other logger frame, wait for the response
+                continue;
+            }
+
+            if payload_len < Self::RESPONSE_PAYLOAD_LEN + 5 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Solarman response without Modbus frame, check the logger serial number",
+                ));
+            }
+
+            return Ok(
+                frame[Self::HEADER_LEN + Self::RESPONSE_PAYLOAD_LEN..checksum_index].to_vec(),
+            );
+        }
+    }
+
+    /// Send a Modbus RTU request through the logger, and return the Modbus response without its CRC
+    async fn modbus_request(&self, request: &[u8]) -> io::Result<Vec<u8>> {
+        let mut modbus_frame = Vec::with_capacity(request.len() + 3);
+        modbus_frame.push(self.config.slave_id);
+        modbus_frame.extend_from_slice(request);
+        modbus_frame.extend(Self::modbus_crc(&modbus_frame).to_le_bytes());
+
+        let mut stream = TcpStream::connect(self.config.get_address()).await?;
+        stream.write_all(&self.encode(&modbus_frame)).await?;
+        let response = Self::read_response(&mut stream).await?;
+
+        let crc_index = response.len() - 2;
+        if Self::modbus_crc(&response[..crc_index])
+            != u16::from_le_bytes([response[crc_index], response[crc_index + 1]])
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid Modbus response CRC",
+            ));
+        }
+
+        if response[1] & 0x80 != 0 {
+            return Err(io::Error::other(format!(
+                "Modbus exception {:#04x} for function {:#04x}",
+                response[2],
+                response[1] & 0x7F
+            )));
+        } else if response[0] != self.config.slave_id || response[1] != request[0] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Unexpected Modbus response from slave {} for function {:#04x}",
+                    response[0], response[1]
+                ),
+            ));
+        }
+
+        Ok(response[1..crc_index].to_vec())
+    }
+
+    /// Send a Modbus RTU request through the logger with the configured timeout
+    async fn modbus_call(&self, request: &[u8]) -> io::Result<Vec<u8>> {
+        tokio::time::timeout(self.config.get_timeout(), self.modbus_request(request))
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "Solarman logger timeout"))?
+    }
+
+    /// Read holding registers of the inverter
+    async fn read_holding_registers(
+        &self,
+        start: u16,
+        count: u16,
+    ) -> io::Result<DeyeSolarRegisters> {
+        let mut request = Vec::with_capacity(5);
+        request.push(Self::MODBUS_READ_HOLDING_REGISTERS);
+        request.extend(start.to_be_bytes());
+        request.extend(count.to_be_bytes());
+
+        let response = self.modbus_call(&request).await?;
+
+        // Function code, byte count, then the registers values
+        let data = response.get(2..).unwrap_or_default();
+        if response.get(1).copied().unwrap_or_default() as usize != data.len()
+            || data.len() != count as usize * 2
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid Modbus registers length {}", data.len()),
+            ));
+        }
+
+        Ok(DeyeSolarRegisters {
+            start,
+            values: data
+                .chunks_exact(2)
+                .map(|reg| u16::from_be_bytes([reg[0], reg[1]]))
+                .collect(),
         })
     }
+
+    /// Write holding registers of the inverter
+    async fn write_holding_registers(&self, start: u16, values: &[u16]) -> io::Result<()> {
+        let mut request = Vec::with_capacity(6 + values.len() * 2);
+        request.push(Self::MODBUS_WRITE_MULTIPLE_REGISTERS);
+        request.extend(start.to_be_bytes());
+        request.extend((values.len() as u16).to_be_bytes());
+        request.push((values.len() * 2) as u8);
+        for value in values {
+            request.extend(value.to_be_bytes());
+        }
+
+        // The response echo the start register and the number of registers written
+        let response = self.modbus_call(&request).await?;
+        if response.get(1..5) != request.get(1..5) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid Modbus write response",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Do a control action on the inverter, confirmed by reading back the written value
+    async fn control(&self, control: &DeyeSolarControl) -> io::Result<()> {
+        let (register, value) = control.get_register();
+        self.write_holding_registers(register, &[value]).await?;
+
+        let read_value = self
+            .read_holding_registers(register, 1)
+            .await?
+            .get(register);
+        if read_value == Some(value) {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Read back value {read_value:?} of register {register:#06x} differs from the written value {value}"
+                ),
+            ))
+        }
+    }
+
+    /// Read the inverter data
+    async fn read_data(&self) -> io::Result<DeyeSolarData> {
+        let registers = self
+            .read_holding_registers(DeyeSolarRegisters::START, DeyeSolarRegisters::COUNT)
+            .await?;
+        DeyeSolarData::from_registers(&registers, self.config.pv_inputs)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Request processed by the Solarman task
+#[derive(Debug)]
+enum DeyeSolarmanRequest {
+    /// Read the inverter data, and publish them to the metrics
+    Read,
+    Control(DeyeSolarControl),
+    /// Reset the metrics once the inverter is inactive (after the pending read)
+    Reset,
+}
+
+/// Response of the Solarman task
+#[derive(Debug)]
+enum DeyeSolarmanResponse {
+    /// Result of a read, whose data are already published
+    Read(io::Result<()>),
+    Control(DeyeSolarControl, io::Result<()>),
+}
+
+/// Control action requested to the Deye inverter by the control service
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeyeSolarControl {
+    /// Limit the output power to a percentage of the rated power
+    PowerLimit(u16),
+    /// Switch on or off the inverter
+    Power(bool),
+}
+
+impl DeyeSolarControl {
+    /// TVF field of the power limit percentage (0 to 100)
+    pub const POWER_LIMIT_PERCENT: usize = 2;
+    /// TVF field to switch on (1) or off (0) the inverter
+    pub const POWER_ON: usize = 2;
+
+    /// Getter of the register to write for the action, with its value
+    fn get_register(&self) -> (u16, u16) {
+        match self {
+            DeyeSolarControl::PowerLimit(percent) => (DeyeSolarRegisters::POWER_LIMIT, *percent),
+            DeyeSolarControl::Power(on) => {
+                (DeyeSolarRegisters::POWER_SWITCH, if *on { 1 } else { 2 })
+            }
+        }
+    }
+}
+
+impl ControlAction for DeyeSolarControl {
+    type Config = DeyeSolarControlConfig;
+
+    fn parse<M>(action: &str, msg: &M) -> Result<DeyeSolarControl, String>
+    where
+        M: Tvf,
+    {
+        match action {
+            "power_limit" => {
+                let percent = msg
+                    .get_unsigned(DeyeSolarControl::POWER_LIMIT_PERCENT)
+                    .map_err(|e| format!("invalid power limit field: {e}"))?;
+                if percent > 100 {
+                    Err(format!("invalid power limit {percent}%"))
+                } else {
+                    Ok(DeyeSolarControl::PowerLimit(percent as u16))
+                }
+            }
+            "power" => Ok(DeyeSolarControl::Power(
+                msg.get_unsigned(DeyeSolarControl::POWER_ON)
+                    .map_err(|e| format!("invalid power on field: {e}"))?
+                    != 0,
+            )),
+            action => Err(format!("unknown action `{action}`")),
+        }
+    }
+
+    fn get_name(&self) -> &'static str {
+        match self {
+            DeyeSolarControl::PowerLimit(_) => "power_limit",
+            DeyeSolarControl::Power(_) => "power",
+        }
+    }
+
+    fn is_allowed(&self, config: &DeyeSolarControlConfig) -> bool {
+        match self {
+            DeyeSolarControl::PowerLimit(_) => config.power_limit,
+            DeyeSolarControl::Power(_) => config.power,
+        }
+    }
+}
+
+/// Deye control actions allowed, every action is disabled by default
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct DeyeSolarControlConfig {
+    /// Allow to limit the output power
+    #[serde(default)]
+    pub power_limit: bool,
+    /// Allow to switch on or off the inverter
+    #[serde(default)]
+    pub power: bool,
+}
+
+/// Configuration of the Solarman V5 mode, to read the inverter registers through the data logger
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeyeSolarmanConfig {
+    /// Address of the data logger (`host` or `host:port`, port 8899 by default)
+    pub address: String,
+    /// Serial number of the data logger
+    pub logger_serial: u32,
+    /// Modbus slave id of the inverter
+    #[serde(default = "DeyeSolarmanConfig::get_default_slave_id")]
+    pub slave_id: u8,
+    /// Timeout in seconds of a request to the data logger
+    #[serde(default = "DeyeSolarmanConfig::get_default_timeout")]
+    pub timeout: u64,
+    /// Number of PV inputs of the inverter (up to 4)
+    #[serde(default = "DeyeSolarmanConfig::get_default_pv_inputs")]
+    pub pv_inputs: u8,
+}
+
+impl DeyeSolarmanConfig {
+    fn get_default_slave_id() -> u8 {
+        1
+    }
+
+    fn get_default_timeout() -> u64 {
+        10
+    }
+
+    fn get_default_pv_inputs() -> u8 {
+        4
+    }
+
+    /// Getter of the data logger address with its port
+    pub fn get_address(&self) -> String {
+        if self.address.contains(':') {
+            self.address.clone()
+        } else {
+            format!("{}:8899", self.address)
+        }
+    }
+
+    /// Getter of the timeout of a request to the data logger
+    pub fn get_timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout)
+    }
+}
+
+/// Location of the inverter, to compute the sunrise and sunset
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeyeSolarLocation {
+    /// Latitude in degrees (positive to the north)
+    pub latitude: f64,
+    /// Longitude in degrees (positive to the east)
+    pub longitude: f64,
+    /// Margin in minutes before the sunrise and after the sunset to fetch the inverter
+    #[serde(default = "DeyeSolarLocation::get_default_margin")]
+    pub margin: i64,
+}
+
+impl DeyeSolarLocation {
+    fn get_default_margin() -> i64 {
+        30
+    }
+
+    /// Compute the sunrise and sunset of a day with the [sunrise equation](https://en.wikipedia.org/wiki/Sunrise_equation).
+    /// During polar days the sun is up all day, and during polar nights it only "rise" at the solar noon.
+    pub fn get_sun_period(&self, date: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+        const J2000: f64 = 2451545.0;
+        const UNIX_EPOCH_JULIAN_DAY: f64 = 2440587.5;
+
+        // Julian days since 2000-01-01 12:00 UTC, at the mean solar noon of the location
+        let days = date
+            .signed_duration_since(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap())
+            .num_days() as f64;
+        let mean_solar_noon = days - self.longitude / 360f64;
+
+        let mean_anomaly = (357.5291 + 0.98560028 * mean_solar_noon).rem_euclid(360f64);
+        let mean_anomaly_rad = mean_anomaly.to_radians();
+        let center = 1.9148 * mean_anomaly_rad.sin()
+            + 0.0200 * (2f64 * mean_anomaly_rad).sin()
+            + 0.0003 * (3f64 * mean_anomaly_rad).sin();
+        let ecliptic_longitude = (mean_anomaly + center + 180f64 + 102.9372)
+            .rem_euclid(360f64)
+            .to_radians();
+        let solar_transit = J2000 + mean_solar_noon + 0.0053 * mean_anomaly_rad.sin()
+            - 0.0069 * (2f64 * ecliptic_longitude).sin();
+
+        let declination_sin = ecliptic_longitude.sin() * 23.4397f64.to_radians().sin();
+        let declination_cos = (1f64 - declination_sin * declination_sin).sqrt();
+        let latitude = self.latitude.to_radians();
+        let hour_angle_cos = ((-0.833f64).to_radians().sin() - latitude.sin() * declination_sin)
+            / (latitude.cos() * declination_cos);
+        let hour_angle = hour_angle_cos.clamp(-1f64, 1f64).acos().to_degrees();
+
+        let to_utc = |julian_day: f64| {
+            DateTime::from_timestamp(
+                ((julian_day - UNIX_EPOCH_JULIAN_DAY) * 86400f64).round() as i64,
+                0,
+            )
+            .unwrap_or_default()
+        };
+        (
+            to_utc(solar_transit - hour_angle / 360f64),
+            to_utc(solar_transit + hour_angle / 360f64),
+        )
+    }
+
+    /// Getter of the date at the location, from its mean solar time (the longitude gives 4 minutes per degree).
+    /// It can differ from the civil date by about an hour, but the sun is never up at midnight of the mean solar time except during polar days.
+    pub fn get_date(&self, now: DateTime<Utc>) -> NaiveDate {
+        (now + TimeDelta::seconds((self.longitude * 240f64).round() as i64)).date_naive()
+    }
+
+    /// Getter of the period to fetch the inverter for a day (from sunrise to sunset with the margin)
+    pub fn get_active_period(&self, date: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+        let (sunrise, sunset) = self.get_sun_period(date);
+        let margin = TimeDelta::minutes(self.margin);
+        (sunrise - margin, sunset + margin)
+    }
+}
+
+/// Configuration of the Deye adaptor (loaded from the processor `adaptor_config_path`)
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct DeyeSolarAdaptorConfig {
+    /// Solarman V5 mode, to read the inverter registers instead of the status page
+    pub solarman: Option<DeyeSolarmanConfig>,
+    /// Control actions allowed on the inverter (only available in Solarman V5 mode)
+    pub control: Option<ControlConfig<DeyeSolarControlConfig>>,
+    /// Location of the inverter, to only fetch it between the sunrise and the sunset
+    pub location: Option<DeyeSolarLocation>,
 }
 
 /// Adaptor for [Deye](https://deye.com/fr/product/sun-m60-80-100g4-eu-q0/) solar inverter
 #[derive(Adaptor)]
 pub struct FetcherDeyeSolarAdaptor {
+    config: DeyeSolarAdaptorConfig,
     uri_fetch: Uri,
     serial_number: Option<String>,
     canceled_counter: u64,
+    /// A Solarman read is in progress
+    solarman_pending: bool,
+    /// Requests to the Solarman task (only in Solarman V5 mode)
+    solarman_tx: Option<mpsc::UnboundedSender<DeyeSolarmanRequest>>,
+    /// Responses of the Solarman task (only in Solarman V5 mode)
+    solarman_rx: Option<mpsc::UnboundedReceiver<DeyeSolarmanResponse>>,
+    /// Control results to send, with the polling of the control service
+    poller: ControlPoller<Infallible, DeyeSolarControl>,
+    /// Period of the day to fetch the inverter, computed from the location
+    active_period: Option<(NaiveDate, DateTime<Utc>, DateTime<Utc>)>,
 
     // Observability
     meter_solar: watch::Sender<DeyeSolarData>,
 }
 
+impl FetcherDeyeSolarAdaptor {
+    /// Method to know if the sun is up (with the margin) at the location of the inverter.
+    /// Always true if no location is set.
+    fn is_sun_up(&mut self) -> bool {
+        let Some(location) = &self.config.location else {
+            return true;
+        };
+
+        let now = Utc::now();
+        let today = location.get_date(now);
+        if self
+            .active_period
+            .is_none_or(|(period_date, _, _)| period_date != today)
+        {
+            let (start, end) = location.get_active_period(today);
+            debug!("Deye active period of {today} from {start} to {end}");
+            self.active_period = Some((today, start, end));
+        }
+
+        self.active_period
+            .is_some_and(|(_, start, end)| now >= start && now <= end)
+    }
+
+    /// Method to reset the metrics once the inverter is inactive
+    fn reset_metrics(&mut self) {
+        if let Some(solarman_tx) = &self.solarman_tx {
+            // The Solarman task publishes the data, so the reset is ordered after its pending read
+            let _ = solarman_tx.send(DeyeSolarmanRequest::Reset);
+        } else if let Some(serial_number) = self.serial_number.take() {
+            // Send empty data to reset metrics
+            let _ = self.meter_solar.send(DeyeSolarData::new(serial_number));
+        }
+    }
+
+    /// Method to process the responses of the Solarman task
+    fn process_solarman_responses<M>(&mut self) -> Result<(), FetcherError<M>>
+    where
+        M: std::marker::Send,
+    {
+        let Some(solarman_rx) = self.solarman_rx.as_mut() else {
+            return Ok(());
+        };
+
+        loop {
+            match solarman_rx.try_recv() {
+                Ok(DeyeSolarmanResponse::Read(result)) => {
+                    self.solarman_pending = false;
+                    match result {
+                        Ok(()) => self.canceled_counter = 0,
+                        Err(e) => {
+                            self.canceled_counter += 1;
+
+                            // If there is too much errors during 5 minutes
+                            if self.canceled_counter > 5 {
+                                warn!("Solarman error {:?}", e);
+                                return Err(FetcherError::Io(e));
+                            } else {
+                                debug!("Solarman error {:?}", e);
+                            }
+                        }
+                    }
+                }
+                Ok(DeyeSolarmanResponse::Control(control, result)) => {
+                    if let Err(e) = &result {
+                        warn!("Deye control action `{}` failed: {e}", control.get_name());
+                    }
+
+                    self.poller.push_result(&control, result.is_ok());
+                }
+                Err(mpsc::error::TryRecvError::Empty) => return Ok(()),
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    return Err(FetcherError::Other("Solarman task stopped".to_string()));
+                }
+            }
+        }
+    }
+
+    /// Method to send a control action received from the control service to the Solarman task.
+    /// The result is sent to the control service once the value is read back from the inverter.
+    fn process_control<M>(&mut self, control: DeyeSolarControl) -> FetchAction<M>
+    where
+        M: Tvf + std::default::Default + std::marker::Send,
+    {
+        if !self.solarman_tx.as_ref().is_some_and(|solarman_tx| {
+            solarman_tx
+                .send(DeyeSolarmanRequest::Control(control.clone()))
+                .is_ok()
+        }) {
+            self.poller.push_result(&control, false);
+        }
+
+        self.poller.next_action()
+    }
+}
+
 impl<M> FetcherAdaptor<M> for FetcherDeyeSolarAdaptor
 where
     M: 'static
@@ -109,6 +970,14 @@ where
         + std::default::Default,
 {
     fn new(proc: &FetcherProc<M>) -> Result<Self, FetcherError<M>> {
+        let config = if proc.settings.get_adaptor_config_path().is_some() {
+            proc.settings
+                .get_adaptor_config::<DeyeSolarAdaptorConfig>()
+                .map_err(|e| FetcherError::Other(format!("Can't read Deye adaptor config: {e}")))?
+        } else {
+            DeyeSolarAdaptorConfig::default()
+        };
+
         let (meter_solar, watch_solar) = watch::channel(DeyeSolarData::default());
 
         let watch_power = watch_solar.clone();
@@ -163,6 +1032,159 @@ where
             })
             .build();
 
+        let watch_pv = watch_solar.clone();
+        let _observable_pv = proc
+            .get_proc_param()
+            .meter("deye_solar")
+            .f64_observable_gauge("prosa_deye_solar_pv")
+            .with_description("DC information (V, A, W) of the Deye inverter PV inputs")
+            .with_callback(move |observer| {
+                let solar_data = watch_pv.borrow();
+                if !solar_data.serial_number.is_empty() {
+                    for (index, pv) in solar_data.pv.iter().enumerate() {
+                        for (pv_type, value) in [
+                            ("voltage", pv.voltage),
+                            ("current", pv.current),
+                            ("power", pv.get_power()),
+                        ] {
+                            observer.observe(
+                                value,
+                                &[
+                                    KeyValue::new("sn", solar_data.serial_number.clone()),
+                                    KeyValue::new("input", (index + 1) as i64),
+                                    KeyValue::new("type", pv_type),
+                                ],
+                            );
+                        }
+                    }
+                }
+            })
+            .build();
+
+        let watch_pv_yield = watch_solar.clone();
+        let _observable_pv_yield = proc
+            .get_proc_param()
+            .meter("deye_solar")
+            .f64_observable_counter("prosa_deye_solar_pv_yield")
+            .with_description("Power yield (kWh) of the Deye inverter PV inputs")
+            .with_callback(move |observer| {
+                let solar_data = watch_pv_yield.borrow();
+                if !solar_data.serial_number.is_empty() {
+                    for (index, pv) in solar_data.pv.iter().enumerate() {
+                        for (yield_type, value) in
+                            [("daily", pv.yield_today), ("total", pv.total_yield)]
+                        {
+                            if value > 0f64 {
+                                observer.observe(
+                                    value,
+                                    &[
+                                        KeyValue::new("sn", solar_data.serial_number.clone()),
+                                        KeyValue::new("input", (index + 1) as i64),
+                                        KeyValue::new("type", yield_type),
+                                    ],
+                                );
+                            }
+                        }
+                    }
+                }
+            })
+            .build();
+
+        let watch_grid = watch_solar.clone();
+        let _observable_grid = proc
+            .get_proc_param()
+            .meter("deye_solar")
+            .f64_observable_gauge("prosa_deye_solar_grid")
+            .with_description("AC grid information (V, A, Hz) of the Deye inverter")
+            .with_callback(move |observer| {
+                let solar_data = watch_grid.borrow();
+                if !solar_data.serial_number.is_empty() {
+                    for (grid_type, value) in [
+                        ("voltage", solar_data.grid_voltage),
+                        ("current", solar_data.grid_current),
+                        ("frequency", solar_data.grid_frequency),
+                    ] {
+                        if let Some(value) = value {
+                            observer.observe(
+                                value,
+                                &[
+                                    KeyValue::new("sn", solar_data.serial_number.clone()),
+                                    KeyValue::new("type", grid_type),
+                                ],
+                            );
+                        }
+                    }
+                }
+            })
+            .build();
+
+        let watch_temperature = watch_solar.clone();
+        let _observable_temperature = proc
+            .get_proc_param()
+            .meter("deye_solar")
+            .f64_observable_gauge("prosa_deye_solar_temperature")
+            .with_description("Temperature (°C) of the Deye inverter")
+            .with_callback(move |observer| {
+                let solar_data = watch_temperature.borrow();
+                if !solar_data.serial_number.is_empty()
+                    && let Some(temperature) = solar_data.radiator_temperature
+                {
+                    observer.observe(
+                        temperature,
+                        &[
+                            KeyValue::new("sn", solar_data.serial_number.clone()),
+                            KeyValue::new("type", "radiator"),
+                        ],
+                    );
+                }
+            })
+            .build();
+
+        let watch_state = watch_solar.clone();
+        let _observable_state = proc
+            .get_proc_param()
+            .meter("deye_solar")
+            .u64_observable_gauge("prosa_deye_solar_state")
+            .with_description("Running state of the Deye inverter (0: standby, 1: self-check, 2: normal, 3: alarm, 4: fault)")
+            .with_callback(move |observer| {
+                let solar_data = watch_state.borrow();
+                if !solar_data.serial_number.is_empty()
+                    && let Some(state) = solar_data.running_state
+                {
+                    observer.observe(
+                        state as u64,
+                        &[
+                            KeyValue::new("sn", solar_data.serial_number.clone()),
+                            KeyValue::new("state", get_state_name(state)),
+                        ],
+                    );
+                }
+            })
+            .build();
+
+        let watch_fault = watch_solar.clone();
+        let _observable_fault = proc
+            .get_proc_param()
+            .meter("deye_solar")
+            .u64_observable_gauge("prosa_deye_solar_fault")
+            .with_description("Active faults of the Deye inverter")
+            .with_callback(move |observer| {
+                let solar_data = watch_fault.borrow();
+                if !solar_data.serial_number.is_empty() {
+                    for code in &solar_data.faults {
+                        observer.observe(
+                            1,
+                            &[
+                                KeyValue::new("sn", solar_data.serial_number.clone()),
+                                KeyValue::new("code", format!("F{code:02}")),
+                                KeyValue::new("fault", get_fault_name(*code)),
+                            ],
+                        );
+                    }
+                }
+            })
+            .build();
+
         let _observable_wireless = proc
             .get_proc_param()
             .meter("deye_solar")
@@ -184,17 +1206,68 @@ where
             })
             .build();
 
+        let (solarman_tx, solarman_rx) = if let Some(solarman_config) = &config.solarman {
+            let service_queue = proc.get_proc_param().get_service_queue();
+            let (solarman_tx, solarman_rx) = DeyeSolarmanClient {
+                config: solarman_config.clone(),
+                sequence: 0,
+            }
+            .spawn(meter_solar.clone(), move || {
+                // A fetcher processor can't receive requests, so it's woken up with a response
+                let _ = RequestMsg::new(
+                    DeyeSolarmanClient::WAKE_SERVICE.to_string(),
+                    M::default(),
+                    service_queue.clone(),
+                )
+                .return_to_sender(M::default());
+            });
+            (Some(solarman_tx), Some(solarman_rx))
+        } else if config.control.is_some() {
+            return Err(FetcherError::Other(
+                "Deye control actions are only available in Solarman V5 mode".to_string(),
+            ));
+        } else {
+            (None, None)
+        };
+
+        let poller = ControlPoller::new("Deye", None, config.control.clone());
+
         Ok(FetcherDeyeSolarAdaptor {
+            config,
             uri_fetch: "/status.html".parse::<hyper::Uri>().unwrap(),
             serial_number: None,
             canceled_counter: 0,
+            solarman_pending: false,
+            solarman_tx,
+            solarman_rx,
+            poller,
+            active_period: None,
             meter_solar,
         })
     }
 
     fn fetch(&mut self) -> Result<FetchAction<M>, FetcherError<M>> {
-        // Call HTTP to retrieve consumption
-        Ok(FetchAction::Http)
+        if !self.is_sun_up() {
+            // The inverter is offline during the night
+            self.reset_metrics();
+            Ok(FetchAction::None)
+        } else if self.solarman_tx.is_some() {
+            // Read the registers through the data logger (the results of the previous requests are processed first)
+            self.process_solarman_responses()?;
+            if self.solarman_pending {
+                debug!("Solarman read still in progress");
+            } else if let Some(solarman_tx) = &self.solarman_tx
+                && solarman_tx.send(DeyeSolarmanRequest::Read).is_ok()
+            {
+                self.solarman_pending = true;
+            }
+
+            self.poller.reset();
+            Ok(self.poller.next_action())
+        } else {
+            // Call HTTP to retrieve consumption
+            Ok(FetchAction::Http)
+        }
     }
 
     fn create_http_request(
@@ -295,10 +1368,472 @@ where
         }
     }
 
+    fn process_service_response(
+        &mut self,
+        mut response: prosa::core::msg::ResponseMsg<M>,
+    ) -> Result<FetchAction<M>, FetcherError<M>> {
+        // The Solarman task wakes the adaptor up once a control action is done
+        self.process_solarman_responses()?;
+
+        if let Some(control) = self.poller.process_response(&mut response) {
+            return Ok(self.process_control(control));
+        }
+
+        // Send the next control result
+        Ok(self.poller.next_action())
+    }
+
     fn end_active_period(&mut self) {
-        if let Some(serial_number) = self.serial_number.take() {
-            // Send empty data to reset metrics
-            let _ = self.meter_solar.send(DeyeSolarData::new(serial_number));
+        self.reset_metrics();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use prosa_utils::msg::simple_string_tvf::SimpleStringTvf;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::control;
+    use crate::mock::{fetcher_proc, service_response};
+
+    const LOGGER_SERIAL: u32 = 4012345678;
+
+    /// V5 request (sequence 1) to read the 2 registers of the daily production
+    const READ_REQUEST: [u8; 36] = [
+        0xA5, 0x17, 0x00, 0x10, 0x45, 0x01, 0x00, 0x4E, 0x89, 0x27, 0xEF, 0x02, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x03, 0x00, 0x3C,
+        0x00, 0x02, 0x04, 0x07, 0xA9, 0x15,
+    ];
+    /// V5 response with the daily production registers (`0x0123` and `0x0000`)
+    const READ_RESPONSE: [u8; 36] = [
+        0xA5, 0x17, 0x00, 0x10, 0x15, 0x01, 0x01, 0x4E, 0x89, 0x27, 0xEF, 0x02, 0x01, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x03, 0x04, 0x01, 0x23,
+        0x00, 0x00, 0x0A, 0x05, 0x69, 0x15,
+    ];
+    /// V5 heartbeat sent by the logger
+    const HEARTBEAT: [u8; 14] = [
+        0xA5, 0x01, 0x00, 0x10, 0x47, 0x05, 0x00, 0x4E, 0x89, 0x27, 0xEF, 0x00, 0x4A, 0x15,
+    ];
+
+    fn solarman_client(address: SocketAddr) -> DeyeSolarmanClient {
+        DeyeSolarmanClient {
+            config: DeyeSolarmanConfig {
+                address: address.to_string(),
+                logger_serial: LOGGER_SERIAL,
+                slave_id: 1,
+                timeout: 5,
+                pv_inputs: 2,
+            },
+            sequence: 1,
         }
     }
+
+    /// Start a fake data logger answering every request frame with the bytes returned by the handler
+    async fn fake_logger<F>(mut handler: F) -> SocketAddr
+    where
+        F: FnMut(&[u8]) -> Vec<u8> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0u8; DeyeSolarmanClient::HEADER_LEN];
+                stream.read_exact(&mut request).await.unwrap();
+                let payload_len = u16::from_le_bytes([request[1], request[2]]) as usize;
+                request.resize(DeyeSolarmanClient::HEADER_LEN + payload_len + 2, 0);
+                stream
+                    .read_exact(&mut request[DeyeSolarmanClient::HEADER_LEN..])
+                    .await
+                    .unwrap();
+                stream.write_all(&handler(&request)).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    /// Build a V5 response frame wrapping a Modbus RTU response (without its CRC)
+    fn response_frame(modbus: &[u8]) -> Vec<u8> {
+        let mut frame = vec![DeyeSolarmanClient::FRAME_START];
+        frame.extend(
+            ((DeyeSolarmanClient::RESPONSE_PAYLOAD_LEN + modbus.len() + 2) as u16).to_le_bytes(),
+        );
+        frame.extend(DeyeSolarmanClient::CONTROL_RESPONSE.to_le_bytes());
+        frame.extend([0x01, 0x01]);
+        frame.extend(LOGGER_SERIAL.to_le_bytes());
+        frame.extend([0x02, 0x01]);
+        frame.extend([0u8; DeyeSolarmanClient::RESPONSE_PAYLOAD_LEN - 2]);
+        frame.extend_from_slice(modbus);
+        frame.extend(DeyeSolarmanClient::modbus_crc(modbus).to_le_bytes());
+        frame.push(DeyeSolarmanClient::checksum(&frame[1..]));
+        frame.push(DeyeSolarmanClient::FRAME_END);
+        frame
+    }
+
+    #[test]
+    fn modbus_crc() {
+        assert_eq!(
+            DeyeSolarmanClient::modbus_crc(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0A]),
+            0xCDC5
+        );
+        assert_eq!(
+            DeyeSolarmanClient::modbus_crc(&READ_REQUEST[26..32]).to_le_bytes(),
+            READ_REQUEST[32..34]
+        );
+    }
+
+    #[test]
+    fn v5_checksum() {
+        assert_eq!(DeyeSolarmanClient::checksum(&READ_REQUEST[1..34]), 0xA9);
+        assert_eq!(DeyeSolarmanClient::checksum(&HEARTBEAT[1..12]), 0x4A);
+        assert_eq!(
+            response_frame(&[0x01, 0x03, 0x04, 0x01, 0x23, 0x00, 0x00]),
+            READ_RESPONSE
+        );
+    }
+
+    #[test]
+    fn fault_names() {
+        assert_eq!(get_fault_name(41), "AC line over voltage");
+        assert_eq!(get_fault_name(42), "AC line low voltage");
+        assert_eq!(get_fault_name(45), "AC line U,V over voltage");
+        assert_eq!(get_fault_name(55), "DC busbar voltage too high");
+        assert_eq!(get_fault_name(9), "F09");
+    }
+
+    #[test]
+    fn encode_request() {
+        let client = solarman_client("127.0.0.1:8899".parse().unwrap());
+        assert_eq!(client.encode(&READ_REQUEST[26..34]), READ_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn read_holding_registers() {
+        let addr = fake_logger(|request| {
+            assert_eq!(request, READ_REQUEST);
+            // The heartbeat of the logger is skipped
+            [HEARTBEAT.as_slice(), READ_RESPONSE.as_slice()].concat()
+        })
+        .await;
+
+        let registers = solarman_client(addr)
+            .read_holding_registers(DeyeSolarRegisters::YIELD_TODAY, 2)
+            .await
+            .unwrap();
+        assert_eq!(registers.get(DeyeSolarRegisters::YIELD_TODAY), Some(0x0123));
+        assert_eq!(registers.get(DeyeSolarRegisters::YIELD_TODAY + 1), Some(0));
+        assert_eq!(registers.get(DeyeSolarRegisters::YIELD_TODAY + 2), None);
+    }
+
+    #[tokio::test]
+    async fn read_invalid_frames() {
+        let mut bad_checksum = READ_RESPONSE;
+        bad_checksum[34] ^= 0xFF;
+        let mut bad_crc = READ_RESPONSE;
+        bad_crc[32] ^= 0xFF;
+        bad_crc[34] = DeyeSolarmanClient::checksum(&bad_crc[1..34]);
+        let mut responses = vec![bad_checksum, bad_crc].into_iter();
+        let addr = fake_logger(move |_| responses.next().unwrap().to_vec()).await;
+
+        let client = solarman_client(addr);
+        for error in [
+            "Invalid Solarman frame checksum",
+            "Invalid Modbus response CRC",
+        ] {
+            let e = client
+                .read_holding_registers(DeyeSolarRegisters::YIELD_TODAY, 2)
+                .await
+                .unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(e.to_string(), error);
+        }
+    }
+
+    #[tokio::test]
+    async fn read_data_published() {
+        let mut registers = DeyeSolarRegisters {
+            start: DeyeSolarRegisters::START,
+            values: vec![0; DeyeSolarRegisters::COUNT as usize],
+        };
+        let mut set = |register: u16, value: u16| {
+            registers.values[(register - DeyeSolarRegisters::START) as usize] = value;
+        };
+        for (index, chars) in b"2206123456".chunks(2).enumerate() {
+            set(
+                DeyeSolarRegisters::SERIAL_NUMBER + index as u16,
+                u16::from_be_bytes([chars[0], chars[1]]),
+            );
+        }
+        set(DeyeSolarRegisters::YIELD_TODAY, 125);
+        // 123456 on two registers, low word first
+        set(DeyeSolarRegisters::TOTAL_YIELD, 0xE240);
+        set(DeyeSolarRegisters::TOTAL_YIELD + 1, 0x0001);
+        set(DeyeSolarRegisters::CURRENT_POWER, 4567);
+        set(DeyeSolarRegisters::PV_VOLTAGE_CURRENT, 345);
+        set(DeyeSolarRegisters::PV_VOLTAGE_CURRENT + 1, 52);
+        set(DeyeSolarRegisters::PV_YIELD_TODAY + 1, 64);
+        set(DeyeSolarRegisters::PV_TOTAL_YIELD[1], 1000);
+        set(DeyeSolarRegisters::RUNNING_STATE, 2);
+        set(DeyeSolarRegisters::GRID_VOLTAGE, 2301);
+        set(DeyeSolarRegisters::GRID_FREQUENCY, 5001);
+        set(DeyeSolarRegisters::RADIATOR_TEMPERATURE, 3550);
+        // Fault 41 is the bit 8 of the third fault register
+        set(DeyeSolarRegisters::FAULTS + 2, 1 << 8);
+
+        let addr = fake_logger(move |request| {
+            let modbus = &request
+                [DeyeSolarmanClient::HEADER_LEN + DeyeSolarmanClient::REQUEST_PAYLOAD_LEN..];
+            assert_eq!(
+                modbus[..6],
+                [
+                    0x01,
+                    DeyeSolarmanClient::MODBUS_READ_HOLDING_REGISTERS,
+                    0x00,
+                    DeyeSolarRegisters::START as u8,
+                    0x00,
+                    DeyeSolarRegisters::COUNT as u8
+                ]
+            );
+            let mut response = vec![
+                0x01,
+                DeyeSolarmanClient::MODBUS_READ_HOLDING_REGISTERS,
+                (registers.values.len() * 2) as u8,
+            ];
+            for value in &registers.values {
+                response.extend(value.to_be_bytes());
+            }
+            response_frame(&response)
+        })
+        .await;
+
+        let (meter_solar, mut watch_solar) = watch::channel(DeyeSolarData::default());
+        let (solarman_tx, mut solarman_rx) = solarman_client(addr).spawn(meter_solar, || {});
+
+        // The data are published by the Solarman task as soon as they are read
+        solarman_tx.send(DeyeSolarmanRequest::Read).unwrap();
+        assert!(matches!(
+            solarman_rx.recv().await,
+            Some(DeyeSolarmanResponse::Read(Ok(())))
+        ));
+        {
+            let solar_data = watch_solar.borrow_and_update();
+            assert_eq!(solar_data.serial_number, "2206123456");
+            assert_eq!(solar_data.current_power, 456);
+            assert_eq!(solar_data.yield_today, 12.5);
+            assert_eq!(solar_data.total_yield, 12345.6);
+            assert_eq!(solar_data.pv.len(), 2);
+            assert_eq!(solar_data.pv[0].voltage, 34.5);
+            assert_eq!(solar_data.pv[0].current, 5.2);
+            assert_eq!(solar_data.pv[1].yield_today, 6.4);
+            assert_eq!(solar_data.pv[1].total_yield, 100.0);
+            assert_eq!(solar_data.grid_voltage, Some(230.1));
+            assert_eq!(solar_data.grid_frequency, Some(50.01));
+            assert_eq!(solar_data.radiator_temperature, Some(25.5));
+            assert_eq!(solar_data.running_state, Some(2));
+            assert_eq!(solar_data.faults, vec![41]);
+        }
+
+        // The reset publishes empty data once the pending read is done
+        solarman_tx.send(DeyeSolarmanRequest::Reset).unwrap();
+        watch_solar.changed().await.unwrap();
+        let solar_data = watch_solar.borrow();
+        assert_eq!(solar_data.serial_number, "2206123456");
+        assert_eq!(solar_data.current_power, 0);
+        assert!(solar_data.pv.is_empty());
+    }
+
+    #[test]
+    fn control_without_solarman() {
+        let proc = fetcher_proc(
+            "deye-control-http",
+            "http://127.0.0.1:8080",
+            Some("control:\n  service: DEYE_CONTROL\n  power: true"),
+        );
+        match <FetcherDeyeSolarAdaptor as FetcherAdaptor<SimpleStringTvf>>::new(&proc) {
+            Err(FetcherError::Other(e)) => assert!(e.contains("Solarman V5 mode"), "{e}"),
+            Err(e) => panic!("Unexpected error {e:?}"),
+            Ok(_) => panic!("The control actions need a reply path to the control service"),
+        }
+    }
+
+    #[tokio::test]
+    async fn control_reply() {
+        let mut power_limit = 100u16;
+        let addr = fake_logger(move |request| {
+            let modbus = &request[DeyeSolarmanClient::HEADER_LEN
+                + DeyeSolarmanClient::REQUEST_PAYLOAD_LEN
+                ..request.len() - 4];
+            if modbus[1] == DeyeSolarmanClient::MODBUS_WRITE_MULTIPLE_REGISTERS {
+                // Echo of the start register and the number of registers written
+                power_limit = u16::from_be_bytes([modbus[7], modbus[8]]);
+                response_frame(&modbus[..6])
+            } else {
+                let mut response = vec![
+                    0x01,
+                    DeyeSolarmanClient::MODBUS_READ_HOLDING_REGISTERS,
+                    0x02,
+                ];
+                response.extend(power_limit.to_be_bytes());
+                response_frame(&response)
+            }
+        })
+        .await;
+        let proc = fetcher_proc(
+            "deye-control-reply",
+            "http://127.0.0.1:8080",
+            Some(&format!(
+                "solarman:\n  address: {addr}\n  logger_serial: {LOGGER_SERIAL}\ncontrol:\n  service: DEYE_CONTROL\n  power_limit: true"
+            )),
+        );
+        let mut adaptor = <FetcherDeyeSolarAdaptor as FetcherAdaptor<SimpleStringTvf>>::new(&proc)
+            .expect("Deye adaptor");
+
+        // Replace the Solarman task to know when the adaptor is woken up
+        let (wake_tx, mut wake_rx) = mpsc::unbounded_channel();
+        let (solarman_tx, solarman_rx) =
+            solarman_client(addr).spawn(adaptor.meter_solar.clone(), move || {
+                let _ = wake_tx.send(());
+            });
+        adaptor.solarman_tx = Some(solarman_tx);
+        adaptor.solarman_rx = Some(solarman_rx);
+
+        let FetchAction::Srv(service, msg) = adaptor.poller.next_action::<SimpleStringTvf>() else {
+            panic!("Expected a control poll");
+        };
+        assert_eq!(service, "DEYE_CONTROL");
+        assert_eq!(msg.get_string(control::ACTION).unwrap().as_ref(), "poll");
+
+        // The control service waits for the value to be read back from the inverter
+        let mut msg = SimpleStringTvf::default();
+        msg.put_string(control::ACTION, "power_limit");
+        msg.put_unsigned(DeyeSolarControl::POWER_LIMIT_PERCENT, 50);
+        let action = adaptor
+            .process_service_response(service_response("DEYE_CONTROL", msg))
+            .unwrap();
+        assert!(matches!(action, FetchAction::None));
+
+        // The result is sent as soon as the Solarman task wakes the adaptor up
+        wake_rx.recv().await.unwrap();
+        let action = adaptor
+            .process_service_response(service_response(
+                DeyeSolarmanClient::WAKE_SERVICE,
+                SimpleStringTvf::default(),
+            ))
+            .unwrap();
+        let FetchAction::Srv(service, msg) = action else {
+            panic!("Expected a control result");
+        };
+        assert_eq!(service, "DEYE_CONTROL");
+        assert_eq!(msg.get_string(control::ACTION).unwrap().as_ref(), "result");
+        assert_eq!(
+            msg.get_string(control::RESULT_ACTION).unwrap().as_ref(),
+            "power_limit"
+        );
+        assert_eq!(msg.get_unsigned(control::RESULT_SUCCESS).unwrap(), 1);
+    }
+
+    /// Assert that the time is the reference one within a minute
+    fn assert_time(time: DateTime<Utc>, reference: &str) {
+        let reference = reference.parse::<DateTime<Utc>>().unwrap();
+        assert!(
+            (time - reference).abs() <= TimeDelta::minutes(1),
+            "{time} is not {reference}"
+        );
+    }
+
+    #[test]
+    fn sun_period() {
+        // Reference times of the NOAA solar calculator
+        for (latitude, longitude, date, sunrise, sunset) in [
+            // Paris at the solstices
+            (
+                48.8566,
+                2.3522,
+                "2024-06-21",
+                "2024-06-21T03:47:02Z",
+                "2024-06-21T19:57:58Z",
+            ),
+            (
+                48.8566,
+                2.3522,
+                "2024-12-21",
+                "2024-12-21T07:41:30Z",
+                "2024-12-21T15:56:18Z",
+            ),
+            // New York at the equinox
+            (
+                40.7128,
+                -74.0060,
+                "2024-03-20",
+                "2024-03-20T10:58:29Z",
+                "2024-03-20T23:08:42Z",
+            ),
+            // Sydney in winter, rising the previous day in UTC
+            (
+                -33.8688,
+                151.2093,
+                "2024-06-21",
+                "2024-06-20T21:00:03Z",
+                "2024-06-21T06:53:56Z",
+            ),
+        ] {
+            let location = DeyeSolarLocation {
+                latitude,
+                longitude,
+                margin: 30,
+            };
+            let (start, end) = location.get_sun_period(date.parse().unwrap());
+            assert_time(start, sunrise);
+            assert_time(end, sunset);
+
+            // The inverter is fetched from the margin before the sunrise to the margin after the sunset
+            let (active_start, active_end) = location.get_active_period(date.parse().unwrap());
+            assert_eq!(start - active_start, TimeDelta::minutes(30));
+            assert_eq!(active_end - end, TimeDelta::minutes(30));
+        }
+    }
+
+    #[test]
+    fn sun_period_polar() {
+        let location = DeyeSolarLocation {
+            latitude: 69.6492,
+            longitude: 18.9553,
+            margin: 0,
+        };
+
+        // Solar noons of the NOAA solar calculator in Tromsø
+        // During the polar day, the sun is up for the whole day around the solar noon
+        let (sunrise, sunset) = location.get_sun_period("2024-06-21".parse().unwrap());
+        assert!((sunset - sunrise - TimeDelta::days(1)).abs() <= TimeDelta::seconds(1));
+        assert_time(sunrise + TimeDelta::hours(12), "2024-06-21T10:46:05Z");
+
+        // During the polar night, the sun only "rise" at the solar noon
+        let (sunrise, sunset) = location.get_sun_period("2024-12-21".parse().unwrap());
+        assert_eq!(sunrise, sunset);
+        assert_time(sunrise, "2024-12-21T10:42:28Z");
+    }
+
+    #[test]
+    fn location_date() {
+        // The date follows the mean solar time of the location, not the one of the host
+        let sydney = DeyeSolarLocation {
+            latitude: -33.8688,
+            longitude: 151.2093,
+            margin: 30,
+        };
+        let new_york = DeyeSolarLocation {
+            latitude: 40.7128,
+            longitude: -74.0060,
+            margin: 30,
+        };
+        let now = "2024-06-20T21:30:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(sydney.get_date(now), "2024-06-21".parse().unwrap());
+        assert_eq!(new_york.get_date(now), "2024-06-20".parse().unwrap());
+
+        let now = "2024-06-21T02:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(sydney.get_date(now), "2024-06-21".parse().unwrap());
+        assert_eq!(new_york.get_date(now), "2024-06-20".parse().unwrap());
+    }
 }
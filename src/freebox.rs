@@ -1,30 +1,54 @@
 //! Fetcher adaptor for [Frebbox](https://dev.freebox.fr/sdk/os/#) french internet provider box
 
-use std::{collections::HashMap, convert::Infallible};
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    io,
+    path::PathBuf,
+};
 
-use bytes::{Buf as _, Bytes};
-use hmac::Hmac;
+use base64::{Engine as _, engine::general_purpose::URL_SAFE};
+use bytes::{Buf, Bytes};
+use hmac::{Hmac, Mac as _};
 use http::{Method, Request, Response, StatusCode};
 use http_body_util::{BodyExt as _, Full, combinators::BoxBody};
 use hyper::body::Incoming;
 use opentelemetry::KeyValue;
-use prosa::core::{adaptor::Adaptor, proc::ProcConfig};
+use prosa::core::{
+    adaptor::Adaptor,
+    msg::Tvf,
+    proc::{ProcConfig, ProcSettings as _},
+};
 use prosa_fetcher::{
     adaptor::FetcherAdaptor,
     proc::{FetchAction, FetcherError, FetcherProc, FetcherSettings},
 };
-use serde::Deserialize;
-use serde_json::{Map, Value};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::Value;
 use tokio::sync::watch;
-use tracing::{debug, warn};
+use tracing::{debug, info, warn};
+
+use crate::control::{ControlAction, ControlConfig, ControlPoller, Event};
 
 #[derive(Debug, Default, Copy, Clone, PartialEq)]
 pub enum FreeboxFetchState {
     #[default]
     Connection,
+    ConnectionXdsl,
+    ConnectionFtth,
     System,
     SwitchStatus,
     SwitchPort(u8),
+    WifiAp,
+    WifiStations(u8),
+    WifiChannelUsage(u8),
+    WifiBss,
+    LanBrowser,
+    CallLog,
+    StorageDisk,
+    StoragePartition,
+    /// Control action requested by the control service
+    Control,
     End,
 }
 
@@ -36,6 +60,14 @@ impl FreeboxFetchState {
                 Method::GET,
                 "/api/v4/connection/".parse::<hyper::Uri>().unwrap(),
             )),
+            FreeboxFetchState::ConnectionXdsl => Some((
+                Method::GET,
+                "/api/v4/connection/xdsl/".parse::<hyper::Uri>().unwrap(),
+            )),
+            FreeboxFetchState::ConnectionFtth => Some((
+                Method::GET,
+                "/api/v4/connection/ftth/".parse::<hyper::Uri>().unwrap(),
+            )),
             FreeboxFetchState::System => Some((
                 Method::GET,
                 "/api/v4/system/".parse::<hyper::Uri>().unwrap(),
@@ -50,66 +82,1457 @@ impl FreeboxFetchState {
                     .parse::<hyper::Uri>()
                     .unwrap(),
             )),
+            FreeboxFetchState::WifiAp => Some((
+                Method::GET,
+                "/api/v4/wifi/ap/".parse::<hyper::Uri>().unwrap(),
+            )),
+            FreeboxFetchState::WifiStations(ap_id) => Some((
+                Method::GET,
+                format!("/api/v4/wifi/ap/{ap_id}/stations/")
+                    .parse::<hyper::Uri>()
+                    .unwrap(),
+            )),
+            FreeboxFetchState::WifiChannelUsage(ap_id) => Some((
+                Method::GET,
+                format!("/api/v4/wifi/ap/{ap_id}/channel_usage/")
+                    .parse::<hyper::Uri>()
+                    .unwrap(),
+            )),
+            FreeboxFetchState::WifiBss => Some((
+                Method::GET,
+                "/api/v4/wifi/bss/".parse::<hyper::Uri>().unwrap(),
+            )),
+            FreeboxFetchState::LanBrowser => Some((
+                Method::GET,
+                "/api/v4/lan/browser/pub/".parse::<hyper::Uri>().unwrap(),
+            )),
+            FreeboxFetchState::CallLog => Some((
+                Method::GET,
+                "/api/v4/call/log/".parse::<hyper::Uri>().unwrap(),
+            )),
+            FreeboxFetchState::StorageDisk => Some((
+                Method::GET,
+                "/api/v4/storage/disk/".parse::<hyper::Uri>().unwrap(),
+            )),
+            FreeboxFetchState::StoragePartition => Some((
+                Method::GET,
+                "/api/v4/storage/partition/".parse::<hyper::Uri>().unwrap(),
+            )),
             _ => None,
         }
     }
 
-    pub fn next_state(&self, number_ports: u8) -> FreeboxFetchState {
+    pub fn next_state(
+        &self,
+        media: Option<&str>,
+        switch_port_ids: &[u8],
+        wifi_ap_ids: &[u8],
+    ) -> FreeboxFetchState {
         match self {
-            FreeboxFetchState::Connection => FreeboxFetchState::System,
-            FreeboxFetchState::System => FreeboxFetchState::SwitchStatus,
-            FreeboxFetchState::SwitchStatus => FreeboxFetchState::SwitchPort(number_ports),
-            FreeboxFetchState::SwitchPort(port) => {
-                // Port ID start at 1
-                if *port > 1 {
-                    FreeboxFetchState::SwitchPort(port - 1)
-                } else {
-                    FreeboxFetchState::End
-                }
+            // Line quality depend on the connection media
+            FreeboxFetchState::Connection => match media {
+                Some("xdsl") => FreeboxFetchState::ConnectionXdsl,
+                Some("ftth") => FreeboxFetchState::ConnectionFtth,
+                _ => FreeboxFetchState::System,
+            },
+            FreeboxFetchState::ConnectionXdsl | FreeboxFetchState::ConnectionFtth => {
+                FreeboxFetchState::System
             }
+            FreeboxFetchState::System => FreeboxFetchState::SwitchStatus,
+            FreeboxFetchState::SwitchStatus => switch_port_ids
+                .first()
+                .map(|port_id| FreeboxFetchState::SwitchPort(*port_id))
+                .unwrap_or(FreeboxFetchState::WifiAp),
+            FreeboxFetchState::SwitchPort(port_id) => switch_port_ids
+                .iter()
+                .skip_while(|id| *id != port_id)
+                .nth(1)
+                .map(|next_port_id| FreeboxFetchState::SwitchPort(*next_port_id))
+                .unwrap_or(FreeboxFetchState::WifiAp),
+            FreeboxFetchState::WifiAp => wifi_ap_ids
+                .first()
+                .map(|ap_id| FreeboxFetchState::WifiStations(*ap_id))
+                .unwrap_or(FreeboxFetchState::WifiBss),
+            FreeboxFetchState::WifiStations(ap_id) => FreeboxFetchState::WifiChannelUsage(*ap_id),
+            FreeboxFetchState::WifiChannelUsage(ap_id) => wifi_ap_ids
+                .iter()
+                .skip_while(|id| *id != ap_id)
+                .nth(1)
+                .map(|next_ap_id| FreeboxFetchState::WifiStations(*next_ap_id))
+                .unwrap_or(FreeboxFetchState::WifiBss),
+            FreeboxFetchState::WifiBss => FreeboxFetchState::LanBrowser,
+            FreeboxFetchState::LanBrowser => FreeboxFetchState::CallLog,
+            FreeboxFetchState::CallLog => FreeboxFetchState::StorageDisk,
+            FreeboxFetchState::StorageDisk => FreeboxFetchState::StoragePartition,
             _ => FreeboxFetchState::End,
         }
     }
 }
 
-#[derive(Default, Debug, Clone, Deserialize)]
-pub struct FreeboxApiResponse {
+/// Error code returned by the Freebox API
+///
+/// See [API errors](https://dev.freebox.fr/sdk/os/login/#errors) for the list of codes.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum FreeboxApiError {
+    /// Invalid session token, or no session token sent
+    #[error("Freebox authentication required: {0}")]
+    AuthRequired(String),
+    /// The session is invalid or expired
+    #[error("Freebox session is invalid: {0}")]
+    InvalidSession(String),
+    /// The `app_token` is unknown or has been revoked
+    #[error("Freebox application token is invalid: {0}")]
+    InvalidToken(String),
+    /// The user has not yet granted the `app_token`
+    #[error("Freebox application token is pending: {0}")]
+    PendingToken(String),
+    /// The application doesn't have the permission to access the resource
+    #[error("Freebox permission denied: {0}")]
+    InsufficientRights(String),
+    /// The API is not reachable from outside the LAN
+    #[error("Freebox API denied from external IP: {0}")]
+    DeniedFromExternalIp(String),
+    /// The request is invalid
+    #[error("Freebox invalid request: {0}")]
+    InvalidRequest(String),
+    /// Too many authentication attempts
+    #[error("Freebox rate limited: {0}")]
+    Ratelimited(String),
+    /// New applications can't be registered
+    #[error("Freebox refuse new applications: {0}")]
+    NewAppsDenied(String),
+    /// API access from applications is disabled
+    #[error("Freebox refuse applications: {0}")]
+    AppsDenied(String),
+    /// Internal error of the Freebox
+    #[error("Freebox internal error: {0}")]
+    InternalError(String),
+    /// Response of the Freebox that can't be parsed
+    #[error("Freebox invalid response: {0}")]
+    InvalidResponse(String),
+    /// Other error codes specific to an API
+    #[error("Freebox error `{code}`: {msg}")]
+    Other { code: String, msg: String },
+}
+
+impl FreeboxApiError {
+    /// Create an API error from the Freebox `error_code` and `msg`
+    pub fn new(error_code: Option<String>, msg: Option<String>) -> FreeboxApiError {
+        let msg = msg.unwrap_or_default();
+        match error_code.as_deref() {
+            Some("auth_required") => FreeboxApiError::AuthRequired(msg),
+            Some("invalid_session") => FreeboxApiError::InvalidSession(msg),
+            Some("invalid_token") => FreeboxApiError::InvalidToken(msg),
+            Some("pending_token") => FreeboxApiError::PendingToken(msg),
+            Some("insufficient_rights") => FreeboxApiError::InsufficientRights(msg),
+            Some("denied_from_external_ip") => FreeboxApiError::DeniedFromExternalIp(msg),
+            Some("invalid_request") => FreeboxApiError::InvalidRequest(msg),
+            Some("ratelimited") => FreeboxApiError::Ratelimited(msg),
+            Some("new_apps_denied") => FreeboxApiError::NewAppsDenied(msg),
+            Some("apps_denied") => FreeboxApiError::AppsDenied(msg),
+            Some("internal_error") => FreeboxApiError::InternalError(msg),
+            code => FreeboxApiError::Other {
+                code: code.unwrap_or("unknown").to_string(),
+                msg,
+            },
+        }
+    }
+
+    /// Method to know if the API error is due to an expired or invalid session
+    pub fn is_session_expired(&self) -> bool {
+        matches!(
+            self,
+            FreeboxApiError::AuthRequired(_)
+                | FreeboxApiError::InvalidSession(_)
+                | FreeboxApiError::InvalidToken(_)
+        )
+    }
+
+    /// Getter of the IO error kind, to let the processor know if the error is recoverable
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            FreeboxApiError::AuthRequired(_)
+            | FreeboxApiError::InvalidSession(_)
+            | FreeboxApiError::InvalidToken(_)
+            | FreeboxApiError::PendingToken(_)
+            | FreeboxApiError::InsufficientRights(_)
+            | FreeboxApiError::DeniedFromExternalIp(_)
+            | FreeboxApiError::NewAppsDenied(_)
+            | FreeboxApiError::AppsDenied(_) => io::ErrorKind::PermissionDenied,
+            FreeboxApiError::InvalidRequest(_) => io::ErrorKind::InvalidInput,
+            FreeboxApiError::Ratelimited(_) => io::ErrorKind::WouldBlock,
+            FreeboxApiError::InternalError(_) => io::ErrorKind::Interrupted,
+            FreeboxApiError::InvalidResponse(_) => io::ErrorKind::InvalidData,
+            FreeboxApiError::Other { .. } => io::ErrorKind::Other,
+        }
+    }
+}
+
+impl<M> From<FreeboxApiError> for FetcherError<M>
+where
+    M: std::marker::Send,
+{
+    fn from(e: FreeboxApiError) -> Self {
+        FetcherError::Io(io::Error::new(e.kind(), e))
+    }
+}
+
+/// Freebox API response with a typed result
+#[derive(Debug, Clone, Deserialize)]
+pub struct FreeboxApiResponse<T> {
     success: bool,
-    result: Option<HashMap<String, Value>>,
+    /// The Freebox doesn't send the result if it's an empty list
+    result: Option<T>,
+    msg: Option<String>,
+    error_code: Option<String>,
+}
+
+impl<T> FreeboxApiResponse<T>
+where
+    T: DeserializeOwned,
+{
+    /// Parse a Freebox API response from a reader
+    pub fn from_reader<R>(reader: R) -> Result<FreeboxApiResponse<T>, FreeboxApiError>
+    where
+        R: io::Read,
+    {
+        serde_json::from_reader(reader).map_err(|e| FreeboxApiError::InvalidResponse(e.to_string()))
+    }
+
+    /// Getter of the API result, or the API error if the call didn't succeed
+    pub fn into_result(self) -> Result<Option<T>, FreeboxApiError> {
+        if self.success {
+            Ok(self.result)
+        } else {
+            Err(FreeboxApiError::new(self.error_code, self.msg))
+        }
+    }
+}
+
+/// Deserialize an optional unsigned number that can be sent as a string by the Freebox
+fn deserialize_opt_u64<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::Number(n)) => n.as_u64(),
+        Some(Value::String(s)) => s.parse().ok(),
+        _ => None,
+    })
+}
+
+/// Freebox login challenge
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxLoginChallenge {
+    #[serde(default)]
+    pub logged_in: bool,
+    #[serde(default)]
+    pub challenge: String,
+}
+
+/// Freebox login session
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxLoginSession {
+    #[serde(default)]
+    pub session_token: String,
+    #[serde(default)]
+    pub challenge: String,
+    /// Permissions granted to the application
+    #[serde(default)]
+    pub permissions: HashMap<String, bool>,
+}
+
+/// Freebox application authorization request
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxAuthorize {
+    #[serde(default)]
+    pub app_token: String,
+    #[serde(default)]
+    pub track_id: u64,
+}
+
+/// Freebox application authorization status
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxAuthorizeStatus {
+    /// `unknown`, `pending`, `timeout`, `granted` or `denied`
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub challenge: String,
+}
+
+/// Freebox connection status
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxConnection {
+    /// `ethernet`, `rfc2684` or `pppoatm`
+    #[serde(default, rename = "type")]
+    pub conn_type: String,
+    /// `going_up`, `up`, `going_down` or `down`
+    #[serde(default)]
+    pub state: String,
+    /// `ftth`, `ethernet`, `xdsl` or `backup_4g`
+    #[serde(default)]
+    pub media: String,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub rate_up: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub rate_down: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub bandwidth_up: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub bandwidth_down: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub bytes_up: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub bytes_down: Option<u64>,
+}
+
+impl FreeboxConnection {
+    /// Getter of the connection state as a number (0 for down, 1 for going down, 2 for going up, 3 for up)
+    pub fn get_state(&self) -> u64 {
+        match self.state.as_str() {
+            "up" => 3,
+            "going_up" => 2,
+            "going_down" => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// Freebox system information
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxSystem {
+    #[serde(default)]
+    pub board_name: String,
+    #[serde(default)]
+    pub firmware_version: String,
+    #[serde(default)]
+    pub serial: String,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub uptime_val: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub temp_cpum: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub temp_sw: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub temp_cpub: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub temp_t1: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub temp_t2: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub fan_rpm: Option<u64>,
+}
+
+/// Freebox xDSL line status
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxXdslStatus {
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub protocol: String,
+    #[serde(default)]
+    pub modulation: String,
+    /// Uptime of the line in seconds
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub uptime: Option<u64>,
+}
+
+/// Freebox xDSL statistics of a line direction
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxXdslStats {
+    /// ATM max rate in kbit/s
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub maxrate: Option<u64>,
+    /// ATM rate in kbit/s
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub rate: Option<u64>,
+    /// Signal noise ratio in dB
+    pub snr: Option<f64>,
+    /// Signal noise ratio in 0.1 dB
+    pub snr_10: Option<f64>,
+    /// Line attenuation in dB
+    pub attn: Option<f64>,
+    /// Line attenuation in 0.1 dB
+    pub attn_10: Option<f64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub fec: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub crc: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub hec: Option<u64>,
+}
+
+impl FreeboxXdslStats {
+    /// Getter of the signal noise ratio in dB with the best precision
+    pub fn get_snr(&self) -> Option<f64> {
+        self.snr_10.map(|snr| snr / 10f64).or(self.snr)
+    }
+
+    /// Getter of the line attenuation in dB with the best precision
+    pub fn get_attn(&self) -> Option<f64> {
+        self.attn_10.map(|attn| attn / 10f64).or(self.attn)
+    }
+}
+
+/// Freebox xDSL line information
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxXdsl {
+    #[serde(default)]
+    pub status: FreeboxXdslStatus,
+    #[serde(default)]
+    pub down: FreeboxXdslStats,
+    #[serde(default)]
+    pub up: FreeboxXdslStats,
+}
+
+/// Freebox FTTH line information
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxFtth {
+    #[serde(default)]
+    pub sfp_present: bool,
+    #[serde(default)]
+    pub sfp_alim_ok: bool,
+    #[serde(default)]
+    pub sfp_has_power_report: bool,
+    #[serde(default)]
+    pub sfp_has_signal: bool,
+    #[serde(default)]
+    pub link: bool,
+    #[serde(default)]
+    pub sfp_model: String,
+    #[serde(default)]
+    pub sfp_vendor: String,
+    /// SFP transmit power in 0.01 dBm
+    pub sfp_pwr_tx: Option<i64>,
+    /// SFP receive power in 0.01 dBm
+    pub sfp_pwr_rx: Option<i64>,
+}
+
+/// Line information of the Freebox depending of the connection media
+#[derive(Default, Debug, Clone)]
+pub struct FreeboxLine {
+    pub xdsl: Option<FreeboxXdsl>,
+    pub ftth: Option<FreeboxFtth>,
+}
+
+/// Host attached to a Freebox switch port
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxSwitchHost {
+    #[serde(default)]
+    pub mac: String,
+    #[serde(default)]
+    pub hostname: String,
+}
+
+/// Status of a Freebox switch port
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxSwitchPort {
+    /// Port ID (start at 1)
+    pub id: u8,
+    #[serde(default)]
+    pub link: String,
+    #[serde(default)]
+    pub duplex: String,
+    #[serde(default)]
+    pub mode: String,
+    /// Negociated speed in Mbit/s
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub speed: Option<u64>,
+    #[serde(default)]
+    pub mac_list: Vec<FreeboxSwitchHost>,
+}
+
+impl FreeboxSwitchPort {
+    /// Getter of the metric labels of the port, with the attached host if there is only one
+    pub fn get_labels(&self, labels: &[KeyValue]) -> Vec<KeyValue> {
+        let mut port_labels = Vec::with_capacity(labels.len() + 2);
+        port_labels.push(KeyValue::new("port", self.id.to_string()));
+        if let [host] = self.mac_list.as_slice() {
+            port_labels.push(KeyValue::new("host", host.mac.clone()));
+        }
+        port_labels.extend_from_slice(labels);
+        port_labels
+    }
+}
+
+/// Statistics of a Freebox switch port
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxSwitchPortStats {
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub rx_good_bytes: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub rx_good_packets: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub rx_err_packets: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub tx_bytes: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub tx_packets: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub tx_collisions: Option<u64>,
+}
+
+/// Switch statistics of the Freebox
+#[derive(Default, Debug, Clone)]
+pub struct FreeboxSwitch {
+    pub ports: Vec<FreeboxSwitchPort>,
+    /// Statistics by port ID
+    pub stats: HashMap<u8, FreeboxSwitchPortStats>,
+}
+
+/// Getter of a readable Wi-Fi band label from the Freebox band name
+fn wifi_band_label(band: &str) -> String {
+    match band {
+        "2d4g" => "2.4GHz".to_string(),
+        "5g" => "5GHz".to_string(),
+        "6g" => "6GHz".to_string(),
+        "60g" => "60GHz".to_string(),
+        band => band.to_string(),
+    }
+}
+
+/// Freebox Wi-Fi access point status
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxWifiApStatus {
+    #[serde(default)]
+    pub state: String,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub channel_width: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub primary_channel: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub secondary_channel: Option<u64>,
+}
+
+/// Freebox Wi-Fi access point configuration
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxWifiApConfig {
+    #[serde(default)]
+    pub band: String,
+}
+
+/// Freebox Wi-Fi access point (a radio of the Freebox)
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxWifiAp {
+    pub id: u8,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub status: FreeboxWifiApStatus,
+    #[serde(default)]
+    pub config: FreeboxWifiApConfig,
+}
+
+/// Station connected to a Freebox Wi-Fi access point
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxWifiStation {
+    #[serde(default)]
+    pub mac: String,
+    #[serde(default)]
+    pub hostname: String,
+    #[serde(default)]
+    pub state: String,
+    /// RSSI of the station in dBm
+    pub signal: Option<i64>,
+    /// Receive rate in bytes/s
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub rx_rate: Option<u64>,
+    /// Transmit rate in bytes/s
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub tx_rate: Option<u64>,
+}
+
+/// Channel usage seen by a Freebox Wi-Fi access point
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxWifiChannelUsage {
+    #[serde(default)]
+    pub band: String,
+    pub channel: u64,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub busy_percent: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub tx_percent: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub rx_busy_percent: Option<u64>,
+}
+
+/// Freebox Wi-Fi BSS status
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxWifiBssStatus {
+    #[serde(default)]
+    pub state: String,
+    #[serde(default)]
+    pub sta_count: u64,
+    #[serde(default)]
+    pub authorized_sta_count: u64,
+}
+
+/// Freebox Wi-Fi BSS configuration
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxWifiBssConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub ssid: String,
+}
+
+/// Freebox Wi-Fi BSS (a network broadcasted by an access point)
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxWifiBss {
+    /// BSSID of the network
+    pub id: String,
+    /// ID of the access point broadcasting the network
+    pub phy_id: u8,
+    #[serde(default)]
+    pub status: FreeboxWifiBssStatus,
+    #[serde(default)]
+    pub config: FreeboxWifiBssConfig,
+}
+
+/// Wi-Fi statistics of the Freebox
+#[derive(Default, Debug, Clone)]
+pub struct FreeboxWifi {
+    pub aps: Vec<FreeboxWifiAp>,
+    pub stations: HashMap<u8, Vec<FreeboxWifiStation>>,
+    pub channel_usages: HashMap<u8, Vec<FreeboxWifiChannelUsage>>,
+    pub bss: Vec<FreeboxWifiBss>,
+}
+
+impl FreeboxWifi {
+    /// Getter of the band label of an access point
+    pub fn get_band(&self, ap_id: u8) -> Option<String> {
+        self.aps
+            .iter()
+            .find(|ap| ap.id == ap_id)
+            .map(|ap| wifi_band_label(&ap.config.band))
+    }
+}
+
+/// Disk of the Freebox NAS
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxDisk {
+    #[serde(default)]
+    pub id: u64,
+    /// `internal`, `sata`, `usb` or `unknown`
+    #[serde(default, rename = "type")]
+    pub disk_type: String,
+    #[serde(default)]
+    pub state: String,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub serial: String,
+    /// Temperature of the disk in °C (0 if unknown)
+    #[serde(default)]
+    pub temp: u64,
+    #[serde(default)]
+    pub spinning: bool,
+}
+
+impl FreeboxDisk {
+    /// Getter of the disk labels
+    pub fn get_labels(&self) -> Vec<KeyValue> {
+        vec![
+            KeyValue::new("disk", self.id as i64),
+            KeyValue::new("type", self.disk_type.clone()),
+            KeyValue::new("model", self.model.clone()),
+        ]
+    }
+}
+
+/// Partition of a Freebox NAS disk
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxPartition {
+    #[serde(default)]
+    pub id: u64,
+    #[serde(default)]
+    pub disk_id: u64,
+    /// `error`, `mounted`, `mounting`, `checking`, `umounted`, ...
+    #[serde(default)]
+    pub state: String,
+    #[serde(default)]
+    pub fstype: String,
+    #[serde(default)]
+    pub label: String,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub total_bytes: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub used_bytes: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_u64")]
+    pub free_bytes: Option<u64>,
+}
+
+/// Storage statistics of the Freebox NAS
+#[derive(Default, Debug, Clone)]
+pub struct FreeboxStorage {
+    pub disks: Vec<FreeboxDisk>,
+    pub partitions: Vec<FreeboxPartition>,
+}
+
+/// Layer 2 identifier of a Freebox LAN host
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxLanHostL2Ident {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default, rename = "type")]
+    pub ident_type: String,
+}
+
+/// Host seen on the Freebox LAN
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxLanHost {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub primary_name: String,
+    #[serde(default)]
+    pub vendor_name: String,
+    #[serde(default)]
+    pub host_type: String,
+    #[serde(default)]
+    pub l2ident: FreeboxLanHostL2Ident,
+    #[serde(default)]
+    pub reachable: bool,
+    #[serde(default)]
+    pub active: bool,
+}
+
+impl FreeboxLanHost {
+    /// Getter of the MAC address of the host
+    pub fn get_mac(&self) -> &str {
+        if self.l2ident.ident_type == "mac_address" {
+            &self.l2ident.id
+        } else {
+            self.id
+                .strip_prefix("ether-")
+                .unwrap_or(self.l2ident.id.as_str())
+        }
+    }
+}
+
+/// Call of the Freebox landline
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxCall {
+    #[serde(default)]
+    pub id: u64,
+    /// `missed`, `accepted` or `outgoing`
+    #[serde(default, rename = "type")]
+    pub call_type: String,
+    /// Timestamp of the call
+    #[serde(default)]
+    pub datetime: u64,
+    #[serde(default)]
+    pub number: String,
+    #[serde(default)]
+    pub name: String,
+    /// Duration of the call in seconds
+    #[serde(default)]
+    pub duration: u64,
+    /// The call has not been seen by the user yet
+    #[serde(default)]
+    pub new: bool,
+}
+
+/// Event detected by the Freebox adaptor, sent to the `event_service` as a TVF message
+#[derive(Debug, Clone, PartialEq)]
+pub enum FreeboxEvent {
+    /// A LAN host became reachable or unreachable
+    Presence {
+        mac: String,
+        name: String,
+        vendor: String,
+        reachable: bool,
+    },
+    /// A new call is present in the landline call log
+    Call {
+        call_type: String,
+        number: String,
+        name: String,
+        duration: u64,
+        datetime: u64,
+    },
+}
+
+impl FreeboxEvent {
+    /// TVF field of the event type (`presence` or `call`)
+    pub const EVENT_TYPE: usize = 1;
+    /// TVF field of the host MAC address
+    pub const PRESENCE_MAC: usize = 2;
+    /// TVF field of the host name
+    pub const PRESENCE_NAME: usize = 3;
+    /// TVF field of the host vendor
+    pub const PRESENCE_VENDOR: usize = 4;
+    /// TVF field of the host reachability (1 for reachable, 0 for unreachable)
+    pub const PRESENCE_REACHABLE: usize = 5;
+    /// TVF field of the call type (`missed`, `accepted` or `outgoing`)
+    pub const CALL_TYPE: usize = 2;
+    /// TVF field of the call number
+    pub const CALL_NUMBER: usize = 3;
+    /// TVF field of the call contact name
+    pub const CALL_NAME: usize = 4;
+    /// TVF field of the call duration in seconds
+    pub const CALL_DURATION: usize = 5;
+    /// TVF field of the call timestamp
+    pub const CALL_DATETIME: usize = 6;
+}
+
+impl Event for FreeboxEvent {
+    fn to_tvf<M>(&self) -> M
+    where
+        M: Tvf + std::default::Default,
+    {
+        let mut msg = M::default();
+        match self {
+            FreeboxEvent::Presence {
+                mac,
+                name,
+                vendor,
+                reachable,
+            } => {
+                msg.put_string(FreeboxEvent::EVENT_TYPE, "presence");
+                msg.put_string(FreeboxEvent::PRESENCE_MAC, mac);
+                msg.put_string(FreeboxEvent::PRESENCE_NAME, name);
+                msg.put_string(FreeboxEvent::PRESENCE_VENDOR, vendor);
+                msg.put_unsigned(FreeboxEvent::PRESENCE_REACHABLE, *reachable as u64);
+            }
+            FreeboxEvent::Call {
+                call_type,
+                number,
+                name,
+                duration,
+                datetime,
+            } => {
+                msg.put_string(FreeboxEvent::EVENT_TYPE, "call");
+                msg.put_string(FreeboxEvent::CALL_TYPE, call_type);
+                msg.put_string(FreeboxEvent::CALL_NUMBER, number);
+                msg.put_string(FreeboxEvent::CALL_NAME, name);
+                msg.put_unsigned(FreeboxEvent::CALL_DURATION, *duration);
+                msg.put_unsigned(FreeboxEvent::CALL_DATETIME, *datetime);
+            }
+        }
+
+        msg
+    }
+}
+
+/// Control action requested to the Freebox by the control service
+#[derive(Debug, Clone, PartialEq)]
+pub enum FreeboxControl {
+    /// Reboot the Freebox
+    Reboot,
+    /// Enable or disable the Wi-Fi
+    Wifi(bool),
+    /// Send a Wake-on-LAN packet to a LAN host
+    WakeOnLan {
+        interface: String,
+        mac: String,
+        password: String,
+    },
+}
+
+impl FreeboxControl {
+    /// TVF field to enable (1) or disable (0) the Wi-Fi
+    pub const WIFI_ENABLED: usize = 2;
+    /// TVF field of the MAC address of the host to wake up
+    pub const WOL_MAC: usize = 2;
+    /// TVF field of the LAN interface of the host to wake up (`pub` by default)
+    pub const WOL_INTERFACE: usize = 3;
+    /// TVF field of the Wake-on-LAN password (optional)
+    pub const WOL_PASSWORD: usize = 4;
+
+    /// Getter of the Freebox application permission needed for the action
+    pub fn get_permission(&self) -> &'static str {
+        "settings"
+    }
+
+    /// Getter of the Freebox call to do for the action, with its JSON body
+    pub fn call(&self) -> (Method, hyper::Uri, Option<String>) {
+        match self {
+            FreeboxControl::Reboot => (
+                Method::POST,
+                "/api/v4/system/reboot/".parse::<hyper::Uri>().unwrap(),
+                None,
+            ),
+            FreeboxControl::Wifi(enabled) => (
+                Method::PUT,
+                "/api/v4/wifi/config/".parse::<hyper::Uri>().unwrap(),
+                Some(serde_json::json!({ "enabled": enabled }).to_string()),
+            ),
+            FreeboxControl::WakeOnLan {
+                interface,
+                mac,
+                password,
+            } => (
+                Method::POST,
+                format!("/api/v4/lan/wol/{interface}/")
+                    .parse::<hyper::Uri>()
+                    .unwrap(),
+                Some(serde_json::json!({ "mac": mac, "password": password }).to_string()),
+            ),
+        }
+    }
+}
+
+impl ControlAction for FreeboxControl {
+    type Config = FreeboxControlConfig;
+
+    fn parse<M>(action: &str, msg: &M) -> Result<FreeboxControl, String>
+    where
+        M: Tvf,
+    {
+        match action {
+            "reboot" => Ok(FreeboxControl::Reboot),
+            "wifi" => Ok(FreeboxControl::Wifi(
+                msg.get_unsigned(FreeboxControl::WIFI_ENABLED)
+                    .map_err(|e| format!("invalid Wi-Fi enabled field: {e}"))?
+                    != 0,
+            )),
+            "wol" => Ok(FreeboxControl::WakeOnLan {
+                interface: msg
+                    .get_string(FreeboxControl::WOL_INTERFACE)
+                    .map(|i| i.into_owned())
+                    .unwrap_or_else(|_| "pub".to_string()),
+                mac: msg
+                    .get_string(FreeboxControl::WOL_MAC)
+                    .map_err(|e| format!("invalid Wake-on-LAN MAC field: {e}"))?
+                    .into_owned(),
+                password: msg
+                    .get_string(FreeboxControl::WOL_PASSWORD)
+                    .map(|p| p.into_owned())
+                    .unwrap_or_default(),
+            }),
+            action => Err(format!("unknown action `{action}`")),
+        }
+    }
+
+    fn get_name(&self) -> &'static str {
+        match self {
+            FreeboxControl::Reboot => "reboot",
+            FreeboxControl::Wifi(_) => "wifi",
+            FreeboxControl::WakeOnLan { .. } => "wol",
+        }
+    }
+
+    fn is_allowed(&self, config: &FreeboxControlConfig) -> bool {
+        match self {
+            FreeboxControl::Reboot => config.reboot,
+            FreeboxControl::Wifi(_) => config.wifi,
+            FreeboxControl::WakeOnLan { .. } => config.wol,
+        }
+    }
+}
+
+/// Freebox control actions allowed, every action is disabled by default
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct FreeboxControlConfig {
+    /// Allow to reboot the Freebox
+    #[serde(default)]
+    pub reboot: bool,
+    /// Allow to enable or disable the Wi-Fi
+    #[serde(default)]
+    pub wifi: bool,
+    /// Allow to send Wake-on-LAN packets
+    #[serde(default)]
+    pub wol: bool,
+}
+
+/// Configuration of the Freebox adaptor (loaded from the processor `adaptor_config_path`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct FreeboxAdaptorConfig {
+    /// Application name displayed on the Freebox during the authorization
+    #[serde(default = "FreeboxAdaptorConfig::get_default_app_name")]
+    pub app_name: String,
+    /// Application version sent during the authorization
+    #[serde(default = "FreeboxAdaptorConfig::get_default_app_version")]
+    pub app_version: String,
+    /// Device name displayed on the Freebox during the authorization
+    #[serde(default = "FreeboxAdaptorConfig::get_default_device_name")]
+    pub device_name: String,
+    /// File where the `app_token` is written once granted, and read at startup if no password is set in the URL
+    pub app_token_file: Option<PathBuf>,
+    /// Service where the Freebox events (presence, ...) are sent
+    pub event_service: Option<String>,
+    /// Control actions allowed on the Freebox
+    pub control: Option<ControlConfig<FreeboxControlConfig>>,
 }
 
-impl FreeboxApiResponse {
-    pub fn get_string(&self, key: &str) -> Option<String> {
-        self.result
-            .as_ref()
-            .and_then(|m| m.get(key).and_then(|v| v.as_str().map(|s| s.to_owned())))
+impl FreeboxAdaptorConfig {
+    fn get_default_app_name() -> String {
+        "ProSA".to_string()
+    }
+
+    fn get_default_app_version() -> String {
+        env!("CARGO_PKG_VERSION").to_string()
+    }
+
+    fn get_default_device_name() -> String {
+        "ProSA".to_string()
+    }
+
+    /// Read the `app_token` previously written in the token file
+    pub fn read_app_token(&self) -> Option<String> {
+        self.app_token_file.as_ref().and_then(|path| {
+            std::fs::read_to_string(path)
+                .ok()
+                .map(|token| token.trim().to_string())
+                .filter(|token| !token.is_empty())
+        })
+    }
+}
+
+impl Default for FreeboxAdaptorConfig {
+    fn default() -> Self {
+        FreeboxAdaptorConfig {
+            app_name: Self::get_default_app_name(),
+            app_version: Self::get_default_app_version(),
+            device_name: Self::get_default_device_name(),
+            app_token_file: None,
+            event_service: None,
+            control: None,
+        }
+    }
+}
+
+/// Adaptor for [Freebox](https://dev.freebox.fr/sdk/os/#) french internet provider box
+#[derive(Adaptor)]
+pub struct FetcherFreeboxAdaptor {
+    settings: FetcherSettings,
+    config: FreeboxAdaptorConfig,
+    /// `app_token` obtained through the authorization flow (when no password is set in the URL)
+    app_token: Option<String>,
+    /// Pending authorization `track_id` with its `app_token` not yet granted
+    authorize_track: Option<(u64, String)>,
+    challenge_freebox: Option<String>,
+    session_token: Option<String>,
+    /// Permissions granted to the application, returned by the session login
+    permissions: HashMap<String, bool>,
+    state: FreeboxFetchState,
+    session_renewed: bool,
+    switch: FreeboxSwitch,
+    wifi: FreeboxWifi,
+    storage: FreeboxStorage,
+    /// Reachability of the LAN hosts by MAC address (`None` before the first fetch)
+    lan_hosts_reachable: Option<HashMap<String, bool>>,
+    /// Identifier of the last call seen in the call log (`None` before the first fetch)
+    last_call_id: Option<u64>,
+    /// Events and control results to send, with the polling of the control service
+    poller: ControlPoller<FreeboxEvent, FreeboxControl>,
+    /// Control action in progress
+    control: Option<FreeboxControl>,
+    /// Control actions received during a fetch, done at its end
+    pending_controls: VecDeque<FreeboxControl>,
+
+    // Observability
+    meter_conn: watch::Sender<FreeboxConnection>,
+    meter_line: watch::Sender<FreeboxLine>,
+    meter_system: watch::Sender<FreeboxSystem>,
+    meter_switch: watch::Sender<FreeboxSwitch>,
+    meter_wifi: watch::Sender<FreeboxWifi>,
+    meter_lan: watch::Sender<Vec<FreeboxLanHost>>,
+    meter_calls: watch::Sender<HashMap<String, u64>>,
+    meter_storage: watch::Sender<FreeboxStorage>,
+}
+
+impl FetcherFreeboxAdaptor {
+    /// Method to know if the application need to be authorized on the Freebox to get an `app_token`
+    fn need_authorization(&self) -> bool {
+        self.app_token.is_none() && self.settings.password().ok().flatten().is_none()
+    }
+
+    /// Method to compute the login password from the Freebox challenge
+    fn challenge_password<M>(&self, challenge: &[u8]) -> Result<Option<Bytes>, FetcherError<M>>
+    where
+        M: std::marker::Send,
+    {
+        if let Some(app_token) = &self.app_token {
+            let mut mac =
+                Hmac::<sha1::Sha1>::new_from_slice(app_token.as_bytes()).map_err(|e| {
+                    FetcherError::Other(format!("Crypto error on password challenge {e}"))
+                })?;
+            mac.update(challenge);
+            Ok(Some(Bytes::copy_from_slice(&mac.finalize().into_bytes())))
+        } else {
+            self.settings
+                .challenge_password::<Hmac<sha1::Sha1>, M>(challenge)
+        }
+    }
+
+    /// Method to store the granted `app_token`, and write it in the configured file
+    fn store_app_token(&mut self, app_token: String) {
+        if let Some(path) = &self.config.app_token_file {
+            // The app_token is a secret, so it's only kept in the file
+            match std::fs::write(path, &app_token) {
+                Ok(()) => info!("Freebox application authorized, app_token written to {path:?}"),
+                Err(e) => warn!("Can't write Freebox app_token to {path:?}: {e}"),
+            }
+        } else {
+            info!(
+                "Freebox application authorized, set the URL password to `{}` (base64-url of app_token `{app_token}`)",
+                URL_SAFE.encode(&app_token)
+            );
+        }
+
+        self.app_token = Some(app_token);
+    }
+
+    /// Getter of the next state to call after the current one
+    fn next_state(&self) -> FreeboxFetchState {
+        let switch_port_ids: Vec<u8> = self.switch.ports.iter().map(|port| port.id).collect();
+        let wifi_ap_ids: Vec<u8> = self.wifi.aps.iter().map(|ap| ap.id).collect();
+        self.state.next_state(
+            Some(self.meter_conn.borrow().media.as_str()),
+            &switch_port_ids,
+            &wifi_ap_ids,
+        )
+    }
+
+    /// Method to detect the presence change of LAN hosts
+    fn detect_presence(&mut self, lan_hosts: &[FreeboxLanHost]) {
+        let hosts_reachable: HashMap<String, bool> = lan_hosts
+            .iter()
+            .map(|host| (host.get_mac().to_string(), host.reachable))
+            .collect();
+
+        if let Some(previous_hosts_reachable) = &self.lan_hosts_reachable {
+            for host in lan_hosts {
+                let was_reachable = previous_hosts_reachable
+                    .get(host.get_mac())
+                    .copied()
+                    .unwrap_or_default();
+                if was_reachable != host.reachable {
+                    debug!(
+                        "Freebox LAN host {} ({}) reachable: {}",
+                        host.primary_name,
+                        host.get_mac(),
+                        host.reachable
+                    );
+                    self.poller.push_event(FreeboxEvent::Presence {
+                        mac: host.get_mac().to_string(),
+                        name: host.primary_name.clone(),
+                        vendor: host.vendor_name.clone(),
+                        reachable: host.reachable,
+                    });
+                }
+            }
+        }
+
+        self.lan_hosts_reachable = Some(hosts_reachable);
+    }
+
+    /// Method to count the new calls of the call log, and notify them
+    fn detect_calls(&mut self, mut calls: Vec<FreeboxCall>) {
+        // The call log is sorted from the newest call
+        calls.sort_by_key(|call| call.id);
+        let last_call_id = self.last_call_id;
+        let new_calls: Vec<FreeboxCall> = calls
+            .into_iter()
+            .filter(|call| last_call_id.is_none_or(|id| call.id > id))
+            .collect();
+
+        if let Some(last_call) = new_calls.last() {
+            self.last_call_id = Some(last_call.id);
+            self.meter_calls.send_modify(|calls_total| {
+                for call in &new_calls {
+                    *calls_total.entry(call.call_type.clone()).or_default() += 1;
+                }
+            });
+
+            // Don't notify the calls already in the log at startup
+            if last_call_id.is_some() {
+                for call in new_calls {
+                    debug!(
+                        "Freebox new {} call from {} ({})",
+                        call.call_type, call.number, call.name
+                    );
+                    self.poller.push_event(FreeboxEvent::Call {
+                        call_type: call.call_type,
+                        number: call.number,
+                        name: call.name,
+                        duration: call.duration,
+                        datetime: call.datetime,
+                    });
+                }
+            }
+        } else if last_call_id.is_none() {
+            // Empty call log
+            self.last_call_id = Some(0);
+        }
+    }
+
+    /// Method to get the next action once the current state is processed.
+    /// At the end of all calls, pending events and control results are sent.
+    fn next_action<M>(&mut self) -> FetchAction<M>
+    where
+        M: Tvf + std::default::Default + std::marker::Send,
+    {
+        if self.state != FreeboxFetchState::End {
+            // Call for next state
+            return FetchAction::Http;
+        }
+
+        if let Some(control) = self.pending_controls.pop_front() {
+            return self.process_control(control);
+        }
+
+        self.poller.next_action()
+    }
+
+    /// Method to know if the application has the permission needed by the control action, granted at the session login
+    fn has_control_permission(&self, control: &FreeboxControl) -> bool {
+        if self
+            .permissions
+            .get(control.get_permission())
+            .copied()
+            .unwrap_or_default()
+        {
+            true
+        } else {
+            warn!(
+                "Freebox control action `{}` needs the `{}` permission for the application",
+                control.get_name(),
+                control.get_permission()
+            );
+            false
+        }
+    }
+
+    /// Method to process a control action received from the control service
+    fn process_control<M>(&mut self, control: FreeboxControl) -> FetchAction<M>
+    where
+        M: Tvf + std::default::Default + std::marker::Send,
+    {
+        if !self.has_control_permission(&control) {
+            self.poller.push_result(&control, false);
+            return self.next_action();
+        }
+
+        self.control = Some(control);
+        self.state = FreeboxFetchState::Control;
+        FetchAction::Http
+    }
+
+    /// Method to update the statistics with the current state response
+    fn update_stats<B>(&mut self, body: B) -> Result<(), FreeboxApiError>
+    where
+        B: Buf,
+    {
+        match self.state {
+            FreeboxFetchState::Connection => {
+                let conn = FreeboxApiResponse::<FreeboxConnection>::from_reader(body.reader())?
+                    .into_result()?
+                    .unwrap_or_default();
+                let _ = self.meter_conn.send(conn);
+            }
+            FreeboxFetchState::ConnectionXdsl => {
+                let xdsl = FreeboxApiResponse::<FreeboxXdsl>::from_reader(body.reader())?
+                    .into_result()?
+                    .unwrap_or_default();
+
+                let _ = self.meter_line.send(FreeboxLine {
+                    xdsl: Some(xdsl),
+                    ..Default::default()
+                });
+            }
+            FreeboxFetchState::ConnectionFtth => {
+                let ftth = FreeboxApiResponse::<FreeboxFtth>::from_reader(body.reader())?
+                    .into_result()?
+                    .unwrap_or_default();
+
+                let _ = self.meter_line.send(FreeboxLine {
+                    ftth: Some(ftth),
+                    ..Default::default()
+                });
+            }
+            FreeboxFetchState::System => {
+                let system = FreeboxApiResponse::<FreeboxSystem>::from_reader(body.reader())?
+                    .into_result()?
+                    .unwrap_or_default();
+                let _ = self.meter_system.send(system);
+            }
+            FreeboxFetchState::SwitchStatus => {
+                let ports =
+                    FreeboxApiResponse::<Vec<FreeboxSwitchPort>>::from_reader(body.reader())?
+                        .into_result()?
+                        .unwrap_or_default();
+
+                self.switch = FreeboxSwitch {
+                    ports,
+                    ..Default::default()
+                };
+                if self.switch.ports.is_empty() {
+                    let _ = self.meter_switch.send(self.switch.clone());
+                }
+            }
+            FreeboxFetchState::SwitchPort(port_id) => {
+                let port_stats =
+                    FreeboxApiResponse::<FreeboxSwitchPortStats>::from_reader(body.reader())?
+                        .into_result()?
+                        .unwrap_or_default();
+
+                self.switch.stats.insert(port_id, port_stats);
+                if self
+                    .switch
+                    .ports
+                    .last()
+                    .is_some_and(|port| port.id == port_id)
+                {
+                    // Every port stats have been retrieved
+                    let _ = self.meter_switch.send(self.switch.clone());
+                }
+            }
+            FreeboxFetchState::WifiAp => {
+                let aps = FreeboxApiResponse::<Vec<FreeboxWifiAp>>::from_reader(body.reader())?
+                    .into_result()?
+                    .unwrap_or_default();
+
+                self.wifi = FreeboxWifi {
+                    aps,
+                    ..Default::default()
+                };
+            }
+            FreeboxFetchState::WifiStations(ap_id) => {
+                let stations =
+                    FreeboxApiResponse::<Vec<FreeboxWifiStation>>::from_reader(body.reader())?
+                        .into_result()?
+                        .unwrap_or_default();
+
+                self.wifi.stations.insert(ap_id, stations);
+            }
+            FreeboxFetchState::WifiChannelUsage(ap_id) => {
+                let channel_usages =
+                    FreeboxApiResponse::<Vec<FreeboxWifiChannelUsage>>::from_reader(body.reader())?
+                        .into_result()?
+                        .unwrap_or_default();
+
+                self.wifi.channel_usages.insert(ap_id, channel_usages);
+            }
+            FreeboxFetchState::WifiBss => {
+                let bss = FreeboxApiResponse::<Vec<FreeboxWifiBss>>::from_reader(body.reader())?
+                    .into_result()?
+                    .unwrap_or_default();
+
+                self.wifi.bss = bss;
+                let _ = self.meter_wifi.send(self.wifi.clone());
+            }
+            FreeboxFetchState::LanBrowser => {
+                let lan_hosts =
+                    FreeboxApiResponse::<Vec<FreeboxLanHost>>::from_reader(body.reader())?
+                        .into_result()?
+                        .unwrap_or_default();
+
+                self.detect_presence(&lan_hosts);
+                let _ = self.meter_lan.send(lan_hosts);
+            }
+            FreeboxFetchState::CallLog => {
+                let calls = FreeboxApiResponse::<Vec<FreeboxCall>>::from_reader(body.reader())?
+                    .into_result()?
+                    .unwrap_or_default();
+
+                self.detect_calls(calls);
+            }
+            FreeboxFetchState::StorageDisk => {
+                let disks = FreeboxApiResponse::<Vec<FreeboxDisk>>::from_reader(body.reader())?
+                    .into_result()?
+                    .unwrap_or_default();
+
+                self.storage = FreeboxStorage {
+                    disks,
+                    ..Default::default()
+                };
+            }
+            FreeboxFetchState::StoragePartition => {
+                let partitions =
+                    FreeboxApiResponse::<Vec<FreeboxPartition>>::from_reader(body.reader())?
+                        .into_result()?
+                        .unwrap_or_default();
+
+                self.storage.partitions = partitions;
+                let _ = self.meter_storage.send(self.storage.clone());
+            }
+            FreeboxFetchState::Control => {
+                FreeboxApiResponse::<Value>::from_reader(body.reader())?.into_result()?;
+                if let Some(control) = self.control.take() {
+                    self.poller.push_result(&control, true);
+                }
+            }
+            FreeboxFetchState::End => {}
+        }
+
+        Ok(())
+    }
+
+    /// Method to process the current state response
+    fn process_state_response<M, B>(&mut self, body: B) -> Result<FetchAction<M>, FetcherError<M>>
+    where
+        M: Tvf + std::default::Default + std::marker::Send,
+        B: Buf,
+    {
+        match self.update_stats(body) {
+            Ok(()) => {
+                self.session_renewed = false;
+                self.state = self.next_state();
+                Ok(self.next_action())
+            }
+            Err(e) => self.process_api_error(e),
+        }
     }
 
-    pub fn get_u64(&self, key: &str) -> Option<u64> {
-        self.result.as_ref().and_then(|m| {
-            m.get(key).and_then(|v| match v {
-                Value::Number(n) => n.as_u64(),
-                Value::String(s) => s.parse().ok(),
-                _ => None,
-            })
-        })
-    }
-}
+    /// Method to drop the current session and login again before replaying the current state call
+    fn renew_session<M>(
+        &mut self,
+        api_err: FreeboxApiError,
+    ) -> Result<FetchAction<M>, FetcherError<M>>
+    where
+        M: std::marker::Send,
+    {
+        if self.session_renewed {
+            // The session has just been renewed, so the login is not effective
+            warn!("Freebox session is still rejected after renewal");
+            Err(api_err.into())
+        } else {
+            debug!(
+                "API[{:?}] session expired ({api_err}), login again",
+                self.state
+            );
+            self.challenge_freebox = None;
+            self.session_token = None;
+            self.permissions.clear();
+            self.session_renewed = true;
 
-/// Adaptor for [Freebox](https://dev.freebox.fr/sdk/os/#) french internet provider box
-#[derive(Adaptor)]
-pub struct FetcherFreeboxAdaptor {
-    settings: FetcherSettings,
-    challenge_freebox: Option<String>,
-    session_token: Option<String>,
-    state: FreeboxFetchState,
-    number_ports: u8,
+            // Login again, the current state will be called after
+            Ok(FetchAction::Http)
+        }
+    }
 
-    // Observability
-    meter_conn: watch::Sender<FreeboxApiResponse>,
-    meter_system: watch::Sender<FreeboxApiResponse>,
-    meter_switch: watch::Sender<Vec<Map<String, Value>>>,
-    meter_eth: watch::Sender<Vec<FreeboxApiResponse>>,
+    /// Method to process a Freebox API error response
+    fn process_api_error<M>(
+        &mut self,
+        api_err: FreeboxApiError,
+    ) -> Result<FetchAction<M>, FetcherError<M>>
+    where
+        M: Tvf + std::default::Default + std::marker::Send,
+    {
+        if api_err.is_session_expired() {
+            self.renew_session(api_err)
+        } else if let Some(control) = self.control.take() {
+            // A failed control action must not stop the adaptor
+            warn!(
+                "Freebox control action `{}` failed: {api_err}",
+                control.get_name()
+            );
+            self.poller.push_result(&control, false);
+            self.state = FreeboxFetchState::End;
+            Ok(self.next_action())
+        } else if let FreeboxApiError::InsufficientRights(_) = api_err {
+            // The application is not allowed to call this API (ex: `calls` permission), skip it
+            warn!("API[{:?}] skipped: {api_err}", self.state);
+            self.state = self.next_state();
+            Ok(self.next_action())
+        } else {
+            Err(api_err.into())
+        }
+    }
 }
 
 impl<M> FetcherAdaptor<M> for FetcherFreeboxAdaptor
@@ -127,7 +1550,7 @@ where
     where
         Self: std::marker::Sized,
     {
-        let (meter_conn, watch_conn) = watch::channel(FreeboxApiResponse::default());
+        let (meter_conn, watch_conn) = watch::channel(FreeboxConnection::default());
 
         let watch_conn_byte = watch_conn.clone();
         let _observable_byte = proc
@@ -137,25 +1560,25 @@ where
             .with_description("Freebox byte counter")
             .with_callback(move |observer| {
                 let conn = watch_conn_byte.borrow();
-                if let (Some(conn_type), Some(up)) =
-                    (conn.get_string("type"), conn.get_u64("bytes_up"))
+                if !conn.conn_type.is_empty()
+                    && let Some(up) = conn.bytes_up
                 {
                     observer.observe(
                         up,
                         &[
-                            KeyValue::new("conn", conn_type),
+                            KeyValue::new("conn", conn.conn_type.clone()),
                             KeyValue::new("flow", "send"),
                         ],
                     );
                 }
 
-                if let (Some(conn_type), Some(down)) =
-                    (conn.get_string("type"), conn.get_u64("bytes_down"))
+                if !conn.conn_type.is_empty()
+                    && let Some(down) = conn.bytes_down
                 {
                     observer.observe(
                         down,
                         &[
-                            KeyValue::new("conn", conn_type),
+                            KeyValue::new("conn", conn.conn_type.clone()),
                             KeyValue::new("flow", "recv"),
                         ],
                     );
@@ -171,52 +1594,52 @@ where
             .with_description("Freebox rate gauge")
             .with_callback(move |observer| {
                 let conn = watch_conn_rate.borrow();
-                if let (Some(conn_type), Some(up)) =
-                    (conn.get_string("type"), conn.get_u64("rate_up"))
+                if !conn.conn_type.is_empty()
+                    && let Some(up) = conn.rate_up
                 {
                     observer.observe(
                         up,
                         &[
-                            KeyValue::new("conn", conn_type),
+                            KeyValue::new("conn", conn.conn_type.clone()),
                             KeyValue::new("type", "rate"),
                             KeyValue::new("flow", "send"),
                         ],
                     );
                 }
 
-                if let (Some(conn_type), Some(down)) =
-                    (conn.get_string("type"), conn.get_u64("rate_down"))
+                if !conn.conn_type.is_empty()
+                    && let Some(down) = conn.rate_down
                 {
                     observer.observe(
                         down,
                         &[
-                            KeyValue::new("conn", conn_type),
+                            KeyValue::new("conn", conn.conn_type.clone()),
                             KeyValue::new("type", "rate"),
                             KeyValue::new("flow", "recv"),
                         ],
                     );
                 }
 
-                if let (Some(conn_type), Some(up)) =
-                    (conn.get_string("type"), conn.get_u64("bandwidth_up"))
+                if !conn.conn_type.is_empty()
+                    && let Some(up) = conn.bandwidth_up
                 {
                     observer.observe(
                         up,
                         &[
-                            KeyValue::new("conn", conn_type),
+                            KeyValue::new("conn", conn.conn_type.clone()),
                             KeyValue::new("type", "bandwidth"),
                             KeyValue::new("flow", "send"),
                         ],
                     );
                 }
 
-                if let (Some(conn_type), Some(down)) =
-                    (conn.get_string("type"), conn.get_u64("bandwidth_down"))
+                if !conn.conn_type.is_empty()
+                    && let Some(down) = conn.bandwidth_down
                 {
                     observer.observe(
                         down,
                         &[
-                            KeyValue::new("conn", conn_type),
+                            KeyValue::new("conn", conn.conn_type.clone()),
                             KeyValue::new("type", "bandwidth"),
                             KeyValue::new("flow", "recv"),
                         ],
@@ -234,95 +1657,253 @@ where
             )
             .with_callback(move |observer| {
                 let conn = watch_conn.borrow();
-                if let (Some(conn_type), Some(state)) =
-                    (conn.get_string("type"), conn.get_string("state"))
+                if !conn.conn_type.is_empty() {
+                    observer.observe(
+                        conn.get_state(),
+                        &[KeyValue::new("conn", conn.conn_type.clone())],
+                    );
+                }
+            })
+            .build();
+
+        let (meter_line, watch_line) = watch::channel(FreeboxLine::default());
+        let watch_line_snr = watch_line.clone();
+        let _observable_xdsl_snr = proc
+            .get_proc_param()
+            .meter("freebox")
+            .f64_observable_gauge("prosa_freebox_xdsl_snr")
+            .with_description("Signal noise ratio (dB) of the Freebox xDSL line")
+            .with_callback(move |observer| {
+                if let Some(xdsl) = &watch_line_snr.borrow().xdsl {
+                    if let Some(snr) = xdsl.up.get_snr() {
+                        observer.observe(snr, &[KeyValue::new("flow", "send")]);
+                    }
+
+                    if let Some(snr) = xdsl.down.get_snr() {
+                        observer.observe(snr, &[KeyValue::new("flow", "recv")]);
+                    }
+                }
+            })
+            .build();
+
+        let watch_line_attn = watch_line.clone();
+        let _observable_xdsl_attn = proc
+            .get_proc_param()
+            .meter("freebox")
+            .f64_observable_gauge("prosa_freebox_xdsl_attenuation")
+            .with_description("Attenuation (dB) of the Freebox xDSL line")
+            .with_callback(move |observer| {
+                if let Some(xdsl) = &watch_line_attn.borrow().xdsl {
+                    if let Some(attn) = xdsl.up.get_attn() {
+                        observer.observe(attn, &[KeyValue::new("flow", "send")]);
+                    }
+
+                    if let Some(attn) = xdsl.down.get_attn() {
+                        observer.observe(attn, &[KeyValue::new("flow", "recv")]);
+                    }
+                }
+            })
+            .build();
+
+        let watch_line_errors = watch_line.clone();
+        let _observable_xdsl_errors = proc
+            .get_proc_param()
+            .meter("freebox")
+            .u64_observable_counter("prosa_freebox_xdsl_errors")
+            .with_description("Errors counter of the Freebox xDSL line")
+            .with_callback(move |observer| {
+                if let Some(xdsl) = &watch_line_errors.borrow().xdsl {
+                    for (flow, stats) in [("send", &xdsl.up), ("recv", &xdsl.down)] {
+                        if let Some(crc) = stats.crc {
+                            observer.observe(
+                                crc,
+                                &[KeyValue::new("flow", flow), KeyValue::new("type", "crc")],
+                            );
+                        }
+
+                        if let Some(fec) = stats.fec {
+                            observer.observe(
+                                fec,
+                                &[KeyValue::new("flow", flow), KeyValue::new("type", "fec")],
+                            );
+                        }
+
+                        if let Some(hec) = stats.hec {
+                            observer.observe(
+                                hec,
+                                &[KeyValue::new("flow", flow), KeyValue::new("type", "hec")],
+                            );
+                        }
+                    }
+                }
+            })
+            .build();
+
+        let watch_line_rate = watch_line.clone();
+        let _observable_xdsl_rate = proc
+            .get_proc_param()
+            .meter("freebox")
+            .u64_observable_gauge("prosa_freebox_xdsl_rate")
+            .with_description("Synchronisation rate (kbit/s) of the Freebox xDSL line")
+            .with_callback(move |observer| {
+                if let Some(xdsl) = &watch_line_rate.borrow().xdsl {
+                    for (flow, stats) in [("send", &xdsl.up), ("recv", &xdsl.down)] {
+                        if let Some(rate) = stats.rate {
+                            observer.observe(
+                                rate,
+                                &[KeyValue::new("flow", flow), KeyValue::new("type", "rate")],
+                            );
+                        }
+
+                        if let Some(maxrate) = stats.maxrate {
+                            observer.observe(
+                                maxrate,
+                                &[
+                                    KeyValue::new("flow", flow),
+                                    KeyValue::new("type", "max_rate"),
+                                ],
+                            );
+                        }
+                    }
+                }
+            })
+            .build();
+
+        let watch_line_uptime = watch_line.clone();
+        let _observable_xdsl_uptime = proc
+            .get_proc_param()
+            .meter("freebox")
+            .u64_observable_gauge("prosa_freebox_xdsl_uptime")
+            .with_description("Uptime (s) of the Freebox xDSL line")
+            .with_callback(move |observer| {
+                if let Some(xdsl) = &watch_line_uptime.borrow().xdsl
+                    && let Some(uptime) = xdsl.status.uptime
+                {
+                    observer.observe(
+                        uptime,
+                        &[
+                            KeyValue::new("status", xdsl.status.status.clone()),
+                            KeyValue::new("modulation", xdsl.status.modulation.clone()),
+                        ],
+                    );
+                }
+            })
+            .build();
+
+        let watch_line_sfp = watch_line.clone();
+        let _observable_ftth_sfp = proc
+            .get_proc_param()
+            .meter("freebox")
+            .u64_observable_gauge("prosa_freebox_ftth_status")
+            .with_description("Status of the Freebox FTTH SFP (1 for ok, 0 otherwise)")
+            .with_callback(move |observer| {
+                if let Some(ftth) = &watch_line_sfp.borrow().ftth {
+                    observer.observe(ftth.sfp_present as u64, &[KeyValue::new("type", "present")]);
+                    observer.observe(ftth.sfp_alim_ok as u64, &[KeyValue::new("type", "alim")]);
+                    observer.observe(
+                        ftth.sfp_has_signal as u64,
+                        &[KeyValue::new("type", "signal")],
+                    );
+                    observer.observe(ftth.link as u64, &[KeyValue::new("type", "link")]);
+                }
+            })
+            .build();
+
+        let _observable_ftth_power = proc
+            .get_proc_param()
+            .meter("freebox")
+            .f64_observable_gauge("prosa_freebox_ftth_power")
+            .with_description("Optical power (dBm) of the Freebox FTTH SFP")
+            .with_callback(move |observer| {
+                if let Some(ftth) = &watch_line.borrow().ftth
+                    && ftth.sfp_has_power_report
                 {
-                    let state_enum = match state.as_str() {
-                        "up" => 3,
-                        "going_up" => 2,
-                        "going_down" => 1,
-                        _ => 0,
-                    };
-                    observer.observe(state_enum, &[KeyValue::new("conn", conn_type)]);
+                    if let Some(pwr_tx) = ftth.sfp_pwr_tx {
+                        observer.observe(pwr_tx as f64 / 100f64, &[KeyValue::new("flow", "send")]);
+                    }
+
+                    if let Some(pwr_rx) = ftth.sfp_pwr_rx {
+                        observer.observe(pwr_rx as f64 / 100f64, &[KeyValue::new("flow", "recv")]);
+                    }
                 }
             })
             .build();
 
-        let (meter_system, watch_system) = watch::channel(FreeboxApiResponse::default());
+        let (meter_system, watch_system) = watch::channel(FreeboxSystem::default());
         let _observable_temp = proc
             .get_proc_param()
             .meter("freebox")
             .u64_observable_gauge("prosa_freebox_temp")
             .with_description("Temperatures of the Freebox")
             .with_callback(move |observer| {
-                let conn = watch_system.borrow();
-                if let (Some(board_name), Some(temp)) =
-                    (conn.get_string("board_name"), conn.get_u64("temp_cpum"))
+                let system = watch_system.borrow();
+                if !system.board_name.is_empty()
+                    && let Some(temp) = system.temp_cpum
                 {
                     observer.observe(
                         temp,
                         &[
-                            KeyValue::new("board", board_name),
+                            KeyValue::new("board", system.board_name.clone()),
                             KeyValue::new("type", "cpum"),
                         ],
                     );
                 }
 
-                if let (Some(board_name), Some(temp)) =
-                    (conn.get_string("board_name"), conn.get_u64("temp_sw"))
+                if !system.board_name.is_empty()
+                    && let Some(temp) = system.temp_sw
                 {
                     observer.observe(
                         temp,
                         &[
-                            KeyValue::new("board", board_name),
+                            KeyValue::new("board", system.board_name.clone()),
                             KeyValue::new("type", "sw"),
                         ],
                     );
                 }
 
-                if let (Some(board_name), Some(temp)) =
-                    (conn.get_string("board_name"), conn.get_u64("temp_cpub"))
+                if !system.board_name.is_empty()
+                    && let Some(temp) = system.temp_cpub
                 {
                     observer.observe(
                         temp,
                         &[
-                            KeyValue::new("board", board_name),
+                            KeyValue::new("board", system.board_name.clone()),
                             KeyValue::new("type", "cpub"),
                         ],
                     );
                 }
 
-                if let (Some(board_name), Some(temp)) =
-                    (conn.get_string("board_name"), conn.get_u64("temp_t1"))
+                if !system.board_name.is_empty()
+                    && let Some(temp) = system.temp_t1
                 {
                     observer.observe(
                         temp,
                         &[
-                            KeyValue::new("board", board_name),
+                            KeyValue::new("board", system.board_name.clone()),
                             KeyValue::new("type", "t1"),
                         ],
                     );
                 }
 
-                if let (Some(board_name), Some(temp)) =
-                    (conn.get_string("board_name"), conn.get_u64("temp_t2"))
+                if !system.board_name.is_empty()
+                    && let Some(temp) = system.temp_t2
                 {
                     observer.observe(
                         temp,
                         &[
-                            KeyValue::new("board", board_name),
+                            KeyValue::new("board", system.board_name.clone()),
                             KeyValue::new("type", "t2"),
                         ],
                     );
                 }
 
-                if let (Some(board_name), Some(rpm)) =
-                    (conn.get_string("board_name"), conn.get_u64("fan_rpm"))
+                if !system.board_name.is_empty()
+                    && let Some(rpm) = system.fan_rpm
                 {
                     observer.observe(
                         rpm,
                         &[
-                            KeyValue::new("board", board_name),
+                            KeyValue::new("board", system.board_name.clone()),
                             KeyValue::new("type", "fan"),
                         ],
                     );
@@ -330,148 +1911,499 @@ where
             })
             .build();
 
-        let (meter_switch, watch_switch) = watch::channel(Vec::<Map<String, Value>>::new());
+        let (meter_switch, watch_switch) = watch::channel(FreeboxSwitch::default());
         let watch_switch_status = watch_switch.clone();
-        let _observable_switch_status =
-            proc.get_proc_param()
-                .meter("freebox")
-                .u64_observable_gauge("prosa_freebox_switch_status")
-                .with_description("Status of the Freebox switch port")
-                .with_callback(move |observer| {
-                    let switch = watch_switch_status.borrow();
-                    for port in switch.iter() {
-                        if let Some(port_id) = port.get("id").and_then(|i| i.as_u64()) {
-                            match port.get("link").and_then(|s| s.as_str()) {
-                                Some("up") => observer
-                                    .observe(1, &[KeyValue::new("port", port_id.to_string())]),
-                                Some("down") => observer
-                                    .observe(0, &[KeyValue::new("port", port_id.to_string())]),
-                                _ => {}
-                            }
-                        }
+        let _observable_switch_status = proc
+            .get_proc_param()
+            .meter("freebox")
+            .u64_observable_gauge("prosa_freebox_switch_status")
+            .with_description("Status of the Freebox switch port")
+            .with_callback(move |observer| {
+                let switch = watch_switch_status.borrow();
+                for port in switch.ports.iter() {
+                    match port.link.as_str() {
+                        "up" => observer.observe(1, &port.get_labels(&[])),
+                        "down" => observer.observe(0, &port.get_labels(&[])),
+                        _ => {}
                     }
-                })
-                .build();
+                }
+            })
+            .build();
 
+        let watch_switch_speed = watch_switch.clone();
         let _observable_switch_speed = proc
             .get_proc_param()
             .meter("freebox")
             .u64_observable_gauge("prosa_freebox_switch_speed")
             .with_description("Negociate speed of the Freebox switch port")
             .with_callback(move |observer| {
-                let switch = watch_switch.borrow();
-                for port in switch.iter() {
-                    if let (Some(port_id), Some(speed)) = (
-                        port.get("id").and_then(|i| i.as_u64()),
-                        port.get("speed")
-                            .and_then(|i| i.as_str().and_then(|s| s.parse().ok())),
-                    ) {
-                        observer.observe(speed, &[KeyValue::new("port", port_id.to_string())]);
+                let switch = watch_switch_speed.borrow();
+                for port in switch.ports.iter() {
+                    if let Some(speed) = port.speed {
+                        observer.observe(
+                            speed,
+                            &port.get_labels(&[
+                                KeyValue::new("duplex", port.duplex.clone()),
+                                KeyValue::new("mode", port.mode.clone()),
+                            ]),
+                        );
                     }
                 }
             })
             .build();
 
-        let (meter_eth, watch_eth) = watch::channel(Vec::<FreeboxApiResponse>::new());
-        let watch_eth_byte = watch_eth.clone();
+        let watch_switch_byte = watch_switch.clone();
         let _observable_eth_byte = proc
             .get_proc_param()
             .meter("freebox")
             .u64_observable_counter("prosa_freebox_switch_bytes")
             .with_description("Bytes process by the Freebox switch port")
             .with_callback(move |observer| {
-                let eth = watch_eth_byte.borrow();
-                for (port_id, port) in eth.iter().enumerate() {
-                    if let Some(rx_bytes) = port.get_u64("rx_good_bytes") {
+                let switch = watch_switch_byte.borrow();
+                for port in switch.ports.iter() {
+                    if let Some(stats) = switch.stats.get(&port.id) {
+                        if let Some(rx_bytes) = stats.rx_good_bytes {
+                            observer.observe(
+                                rx_bytes,
+                                &port.get_labels(&[KeyValue::new("flow", "recv")]),
+                            );
+                        }
+
+                        if let Some(tx_bytes) = stats.tx_bytes {
+                            observer.observe(
+                                tx_bytes,
+                                &port.get_labels(&[KeyValue::new("flow", "send")]),
+                            );
+                        }
+                    }
+                }
+            })
+            .build();
+
+        let _observable_eth_packet = proc
+            .get_proc_param()
+            .meter("freebox")
+            .u64_observable_counter("prosa_freebox_switch_packets")
+            .with_description("Packets process by the Freebox switch port")
+            .with_callback(move |observer| {
+                let switch = watch_switch.borrow();
+                for port in switch.ports.iter() {
+                    if let Some(stats) = switch.stats.get(&port.id) {
+                        if let Some(rx_packets) = stats.rx_good_packets {
+                            observer.observe(
+                                rx_packets,
+                                &port.get_labels(&[KeyValue::new("flow", "recv")]),
+                            );
+                        }
+
+                        if let Some(tx_packets) = stats.tx_packets {
+                            observer.observe(
+                                tx_packets,
+                                &port.get_labels(&[KeyValue::new("flow", "send")]),
+                            );
+                        }
+
+                        if let Some(rx_packets) = stats.rx_err_packets {
+                            observer.observe(
+                                rx_packets,
+                                &port.get_labels(&[KeyValue::new("flow", "recv_err")]),
+                            );
+                        }
+
+                        if let Some(tx_packets) = stats.tx_collisions {
+                            observer.observe(
+                                tx_packets,
+                                &port.get_labels(&[KeyValue::new("flow", "send_err")]),
+                            );
+                        }
+                    }
+                }
+            })
+            .build();
+
+        let config = if proc.settings.get_adaptor_config_path().is_some() {
+            proc.settings
+                .get_adaptor_config::<FreeboxAdaptorConfig>()
+                .map_err(|e| {
+                    FetcherError::Other(format!("Can't read Freebox adaptor config: {e}"))
+                })?
+        } else {
+            FreeboxAdaptorConfig::default()
+        };
+
+        let app_token = if proc.settings.password()?.is_none() {
+            config.read_app_token()
+        } else {
+            None
+        };
+
+        let (meter_wifi, watch_wifi) = watch::channel(FreeboxWifi::default());
+        let watch_wifi_stations = watch_wifi.clone();
+        let _observable_wifi_stations = proc
+            .get_proc_param()
+            .meter("freebox")
+            .u64_observable_gauge("prosa_freebox_wifi_stations")
+            .with_description("Number of stations connected to the Freebox Wi-Fi")
+            .with_callback(move |observer| {
+                let wifi = watch_wifi_stations.borrow();
+                for (ap_id, stations) in wifi.stations.iter() {
+                    if let Some(band) = wifi.get_band(*ap_id) {
+                        observer.observe(
+                            stations.len() as u64,
+                            &[
+                                KeyValue::new("ap", ap_id.to_string()),
+                                KeyValue::new("band", band),
+                            ],
+                        );
+                    }
+                }
+            })
+            .build();
+
+        let watch_wifi_bss_stations = watch_wifi.clone();
+        let _observable_wifi_bss_stations = proc
+            .get_proc_param()
+            .meter("freebox")
+            .u64_observable_gauge("prosa_freebox_wifi_bss_authorized_stations")
+            .with_description("Number of stations authorized on each Freebox Wi-Fi BSS")
+            .with_callback(move |observer| {
+                let wifi = watch_wifi_bss_stations.borrow();
+                for bss in wifi.bss.iter() {
+                    if let Some(band) = wifi.get_band(bss.phy_id) {
+                        observer.observe(
+                            bss.status.authorized_sta_count,
+                            &[
+                                KeyValue::new("ap", bss.phy_id.to_string()),
+                                KeyValue::new("band", band),
+                                KeyValue::new("ssid", bss.config.ssid.clone()),
+                            ],
+                        );
+                    }
+                }
+            })
+            .build();
+
+        let watch_wifi_signal = watch_wifi.clone();
+        let _observable_wifi_signal = proc
+            .get_proc_param()
+            .meter("freebox")
+            .i64_observable_gauge("prosa_freebox_wifi_station_signal")
+            .with_description("RSSI (dBm) of the stations connected to the Freebox Wi-Fi")
+            .with_callback(move |observer| {
+                let wifi = watch_wifi_signal.borrow();
+                for (ap_id, stations) in wifi.stations.iter() {
+                    if let Some(band) = wifi.get_band(*ap_id) {
+                        for station in stations {
+                            if let Some(signal) = station.signal {
+                                observer.observe(
+                                    signal,
+                                    &[
+                                        KeyValue::new("band", band.clone()),
+                                        KeyValue::new("mac", station.mac.clone()),
+                                        KeyValue::new("hostname", station.hostname.clone()),
+                                    ],
+                                );
+                            }
+                        }
+                    }
+                }
+            })
+            .build();
+
+        let watch_wifi_rate = watch_wifi.clone();
+        let _observable_wifi_rate = proc
+            .get_proc_param()
+            .meter("freebox")
+            .u64_observable_gauge("prosa_freebox_wifi_station_rate")
+            .with_description("Rate (bytes/s) of the stations connected to the Freebox Wi-Fi")
+            .with_callback(move |observer| {
+                let wifi = watch_wifi_rate.borrow();
+                for (ap_id, stations) in wifi.stations.iter() {
+                    if let Some(band) = wifi.get_band(*ap_id) {
+                        for station in stations {
+                            if let Some(rx_rate) = station.rx_rate {
+                                observer.observe(
+                                    rx_rate,
+                                    &[
+                                        KeyValue::new("band", band.clone()),
+                                        KeyValue::new("mac", station.mac.clone()),
+                                        KeyValue::new("hostname", station.hostname.clone()),
+                                        KeyValue::new("flow", "recv"),
+                                    ],
+                                );
+                            }
+
+                            if let Some(tx_rate) = station.tx_rate {
+                                observer.observe(
+                                    tx_rate,
+                                    &[
+                                        KeyValue::new("band", band.clone()),
+                                        KeyValue::new("mac", station.mac.clone()),
+                                        KeyValue::new("hostname", station.hostname.clone()),
+                                        KeyValue::new("flow", "send"),
+                                    ],
+                                );
+                            }
+                        }
+                    }
+                }
+            })
+            .build();
+
+        let watch_wifi_channel = watch_wifi.clone();
+        let _observable_wifi_channel = proc
+            .get_proc_param()
+            .meter("freebox")
+            .u64_observable_gauge("prosa_freebox_wifi_channel")
+            .with_description("Channel used by the Freebox Wi-Fi access points")
+            .with_callback(move |observer| {
+                let wifi = watch_wifi_channel.borrow();
+                for ap in wifi.aps.iter() {
+                    let band = wifi_band_label(&ap.config.band);
+                    if let Some(primary_channel) = ap.status.primary_channel {
                         observer.observe(
-                            rx_bytes,
+                            primary_channel,
                             &[
-                                KeyValue::new("port", port_id.to_string()),
-                                KeyValue::new("flow", "recv"),
+                                KeyValue::new("band", band.clone()),
+                                KeyValue::new("type", "primary"),
                             ],
                         );
                     }
 
-                    if let Some(tx_bytes) = port.get_u64("tx_bytes") {
+                    if let Some(secondary_channel) = ap.status.secondary_channel {
                         observer.observe(
-                            tx_bytes,
+                            secondary_channel,
                             &[
-                                KeyValue::new("port", port_id.to_string()),
-                                KeyValue::new("flow", "send"),
+                                KeyValue::new("band", band.clone()),
+                                KeyValue::new("type", "secondary"),
                             ],
                         );
                     }
+
+                    if let Some(channel_width) = ap.status.channel_width {
+                        observer.observe(
+                            channel_width,
+                            &[KeyValue::new("band", band), KeyValue::new("type", "width")],
+                        );
+                    }
+                }
+            })
+            .build();
+
+        let _observable_wifi_usage = proc
+            .get_proc_param()
+            .meter("freebox")
+            .u64_observable_gauge("prosa_freebox_wifi_channel_usage")
+            .with_description(
+                "Utilisation (%) of the channel used by the Freebox Wi-Fi access points",
+            )
+            .with_callback(move |observer| {
+                let wifi = watch_wifi.borrow();
+                for ap in wifi.aps.iter() {
+                    if let (Some(primary_channel), Some(usages)) =
+                        (ap.status.primary_channel, wifi.channel_usages.get(&ap.id))
+                    {
+                        let band = wifi_band_label(&ap.config.band);
+                        for usage in usages.iter().filter(|u| u.channel == primary_channel) {
+                            if let Some(busy) = usage.busy_percent {
+                                observer.observe(
+                                    busy,
+                                    &[
+                                        KeyValue::new("band", band.clone()),
+                                        KeyValue::new("channel", usage.channel.to_string()),
+                                        KeyValue::new("type", "busy"),
+                                    ],
+                                );
+                            }
+
+                            if let Some(tx) = usage.tx_percent {
+                                observer.observe(
+                                    tx,
+                                    &[
+                                        KeyValue::new("band", band.clone()),
+                                        KeyValue::new("channel", usage.channel.to_string()),
+                                        KeyValue::new("type", "send"),
+                                    ],
+                                );
+                            }
+
+                            if let Some(rx_busy) = usage.rx_busy_percent {
+                                observer.observe(
+                                    rx_busy,
+                                    &[
+                                        KeyValue::new("band", band.clone()),
+                                        KeyValue::new("channel", usage.channel.to_string()),
+                                        KeyValue::new("type", "recv"),
+                                    ],
+                                );
+                            }
+                        }
+                    }
+                }
+            })
+            .build();
+
+        let (meter_lan, watch_lan) = watch::channel(Vec::<FreeboxLanHost>::new());
+        let watch_lan_reachable = watch_lan.clone();
+        let _observable_lan_reachable = proc
+            .get_proc_param()
+            .meter("freebox")
+            .u64_observable_gauge("prosa_freebox_lan_host_reachable")
+            .with_description(
+                "Reachability of the hosts on the Freebox LAN (1 for reachable, 0 otherwise)",
+            )
+            .with_callback(move |observer| {
+                let lan_hosts = watch_lan_reachable.borrow();
+                for host in lan_hosts.iter() {
+                    observer.observe(
+                        host.reachable as u64,
+                        &[
+                            KeyValue::new("mac", host.get_mac().to_string()),
+                            KeyValue::new("name", host.primary_name.clone()),
+                            KeyValue::new("vendor", host.vendor_name.clone()),
+                        ],
+                    );
+                }
+            })
+            .build();
+
+        let _observable_lan_hosts = proc
+            .get_proc_param()
+            .meter("freebox")
+            .u64_observable_gauge("prosa_freebox_lan_hosts")
+            .with_description("Number of hosts known on the Freebox LAN")
+            .with_callback(move |observer| {
+                let lan_hosts = watch_lan.borrow();
+                if !lan_hosts.is_empty() {
+                    observer.observe(lan_hosts.len() as u64, &[KeyValue::new("type", "total")]);
+                    observer.observe(
+                        lan_hosts.iter().filter(|h| h.reachable).count() as u64,
+                        &[KeyValue::new("type", "reachable")],
+                    );
+                }
+            })
+            .build();
+
+        let (meter_calls, watch_calls) = watch::channel(HashMap::<String, u64>::new());
+        let _observable_calls = proc
+            .get_proc_param()
+            .meter("freebox")
+            .u64_observable_counter("prosa_freebox_calls_total")
+            .with_description("Number of calls on the Freebox landline")
+            .with_callback(move |observer| {
+                for (call_type, count) in watch_calls.borrow().iter() {
+                    observer.observe(*count, &[KeyValue::new("type", call_type.clone())]);
+                }
+            })
+            .build();
+
+        let (meter_storage, watch_storage) = watch::channel(FreeboxStorage::default());
+        let watch_storage_temp = watch_storage.clone();
+        let _observable_disk_temp = proc
+            .get_proc_param()
+            .meter("freebox")
+            .u64_observable_gauge("prosa_freebox_disk_temp")
+            .with_description("Temperature (°C) of the Freebox NAS disks")
+            .with_callback(move |observer| {
+                for disk in watch_storage_temp.borrow().disks.iter() {
+                    if disk.temp > 0 {
+                        observer.observe(disk.temp, &disk.get_labels());
+                    }
+                }
+            })
+            .build();
+
+        let watch_storage_spinning = watch_storage.clone();
+        let _observable_disk_spinning = proc
+            .get_proc_param()
+            .meter("freebox")
+            .u64_observable_gauge("prosa_freebox_disk_spinning")
+            .with_description(
+                "Spinning state of the Freebox NAS disks (1 for spinning, 0 otherwise)",
+            )
+            .with_callback(move |observer| {
+                for disk in watch_storage_spinning.borrow().disks.iter() {
+                    observer.observe(disk.spinning as u64, &disk.get_labels());
                 }
             })
             .build();
 
-        let _observable_eth_packet = proc
+        let _observable_partition_bytes = proc
             .get_proc_param()
             .meter("freebox")
-            .u64_observable_counter("prosa_freebox_switch_packets")
-            .with_description("Packets process by the Freebox switch port")
+            .u64_observable_gauge("prosa_freebox_partition_bytes")
+            .with_description("Size in bytes of the Freebox NAS partitions")
             .with_callback(move |observer| {
-                let eth = watch_eth.borrow();
-                for (port_id, port) in eth.iter().enumerate() {
-                    if let Some(rx_packets) = port.get_u64("rx_good_packets") {
-                        observer.observe(
-                            rx_packets,
-                            &[
-                                KeyValue::new("port", port_id.to_string()),
-                                KeyValue::new("flow", "recv"),
-                            ],
-                        );
-                    }
-
-                    if let Some(tx_packets) = port.get_u64("tx_packets") {
-                        observer.observe(
-                            tx_packets,
-                            &[
-                                KeyValue::new("port", port_id.to_string()),
-                                KeyValue::new("flow", "send"),
-                            ],
-                        );
-                    }
-
-                    if let Some(rx_packets) = port.get_u64("rx_err_packets") {
-                        observer.observe(
-                            rx_packets,
-                            &[
-                                KeyValue::new("port", port_id.to_string()),
-                                KeyValue::new("flow", "recv_err"),
-                            ],
-                        );
-                    }
-
-                    if let Some(tx_packets) = port.get_u64("tx_collisions") {
-                        observer.observe(
-                            tx_packets,
-                            &[
-                                KeyValue::new("port", port_id.to_string()),
-                                KeyValue::new("flow", "send_err"),
-                            ],
-                        );
+                for partition in watch_storage.borrow().partitions.iter() {
+                    for (size_type, size) in [
+                        ("total", partition.total_bytes),
+                        ("used", partition.used_bytes),
+                        ("free", partition.free_bytes),
+                    ] {
+                        if let Some(size) = size {
+                            observer.observe(
+                                size,
+                                &[
+                                    KeyValue::new("disk", partition.disk_id as i64),
+                                    KeyValue::new("partition", partition.label.clone()),
+                                    KeyValue::new("fstype", partition.fstype.clone()),
+                                    KeyValue::new("state", partition.state.clone()),
+                                    KeyValue::new("type", size_type),
+                                ],
+                            );
+                        }
                     }
                 }
             })
             .build();
 
+        let poller = ControlPoller::new(
+            "Freebox",
+            config.event_service.clone(),
+            config.control.clone(),
+        );
         Ok(Self {
             settings: proc.settings.clone(),
+            config,
+            app_token,
+            authorize_track: None,
             challenge_freebox: None,
             session_token: None,
+            permissions: HashMap::new(),
             state: FreeboxFetchState::End,
-            number_ports: 0,
+            session_renewed: false,
+            switch: FreeboxSwitch::default(),
+            wifi: FreeboxWifi::default(),
+            storage: FreeboxStorage::default(),
+            lan_hosts_reachable: None,
+            last_call_id: None,
+            poller,
+            control: None,
+            pending_controls: VecDeque::new(),
             meter_conn,
+            meter_line,
             meter_system,
             meter_switch,
-            meter_eth,
+            meter_wifi,
+            meter_lan,
+            meter_calls,
+            meter_storage,
         })
     }
 
     fn fetch(&mut self) -> Result<FetchAction<M>, FetcherError<M>> {
         // Call HTTP to retrieve statistics with first state
         self.state = FreeboxFetchState::default();
+        self.session_renewed = false;
+        if let Some(control) = self.control.take() {
+            // The control action is interrupted by the new fetch
+            warn!(
+                "Freebox control action `{}` interrupted by a new fetch",
+                control.get_name()
+            );
+            self.poller.push_result(&control, false);
+        }
+        self.poller.reset();
         Ok(FetchAction::Http)
     }
 
@@ -479,7 +2411,45 @@ where
         &self,
         mut request_builder: http::request::Builder,
     ) -> Result<Request<BoxBody<hyper::body::Bytes, Infallible>>, FetcherError<M>> {
-        if self.challenge_freebox.is_none() {
+        if self.need_authorization() {
+            if let Some((track_id, _)) = &self.authorize_track {
+                // Check if the user accepted the application on the Freebox
+                request_builder = request_builder
+                    .method(Method::GET)
+                    .uri(
+                        format!("/api/v4/login/authorize/{track_id}")
+                            .parse::<hyper::Uri>()
+                            .unwrap(),
+                    )
+                    .header(hyper::header::CONNECTION, "keep-alive")
+                    .header(hyper::header::ACCEPT, "application/json");
+                let request = request_builder.body(BoxBody::default())?;
+                Ok(request)
+            } else if let Some(username) = self.settings.username() {
+                // Ask an authorization for the application
+                let json_data = serde_json::json!({
+                    "app_id": username,
+                    "app_name": self.config.app_name,
+                    "app_version": self.config.app_version,
+                    "device_name": self.config.device_name,
+                })
+                .to_string();
+                request_builder = request_builder
+                    .method(Method::POST)
+                    .uri("/api/v4/login/authorize/".parse::<hyper::Uri>().unwrap())
+                    .header(hyper::header::CONNECTION, "keep-alive")
+                    .header(hyper::header::ACCEPT, "application/json")
+                    .header(hyper::header::CONTENT_TYPE, "application/json")
+                    .header(hyper::header::CONTENT_LENGTH, json_data.len().to_string());
+                let request =
+                    request_builder.body(BoxBody::new(Full::new(Bytes::from(json_data))))?;
+                Ok(request)
+            } else {
+                Err(FetcherError::Other(
+                    "Can't get `app_id` for the Freebox authorization".to_string(),
+                ))
+            }
+        } else if self.challenge_freebox.is_none() {
             // Get a challenge to login after
             request_builder = request_builder
                 .method(Method::GET)
@@ -494,8 +2464,7 @@ where
             // Get a session token to login
             if let (Some(username), Some(challenge)) = (
                 self.settings.username(),
-                self.settings
-                    .challenge_password::<Hmac<sha1::Sha1>, M>(challenge_freebox.as_bytes())?,
+                self.challenge_password::<M>(challenge_freebox.as_bytes())?,
             ) {
                 let json_data =
                     format!("{{\"app_id\":\"{username}\",\"password\":\"{challenge:02x}\"}}");
@@ -514,6 +2483,26 @@ where
                     "Can't retrieve `challenge` from remote".to_string(),
                 ))
             }
+        } else if self.state == FreeboxFetchState::Control
+            && let Some(control) = &self.control
+        {
+            // Send the control action
+            let (method, uri, json_data) = control.call();
+            request_builder = request_builder
+                .method(method)
+                .uri(uri)
+                .header(hyper::header::CONNECTION, "keep-alive")
+                .header(hyper::header::ACCEPT, "application/json")
+                .header("X-Fbx-App-Auth", self.session_token.as_ref().unwrap());
+            let request = if let Some(json_data) = json_data {
+                request_builder
+                    .header(hyper::header::CONTENT_TYPE, "application/json")
+                    .header(hyper::header::CONTENT_LENGTH, json_data.len().to_string())
+                    .body(BoxBody::new(Full::new(Bytes::from(json_data))))?
+            } else {
+                request_builder.body(BoxBody::default())?
+            };
+            Ok(request)
         } else if let Some((method, uri)) = self.state.call() {
             // Send request depending of the state
             request_builder = request_builder
@@ -537,7 +2526,88 @@ where
     ) -> Result<FetchAction<M>, FetcherError<M>> {
         match response {
             Ok(response) => {
-                if self.challenge_freebox.is_none() {
+                if self.need_authorization() {
+                    match response.status() {
+                        StatusCode::OK => {
+                            let server = response
+                                .headers()
+                                .get(http::header::SERVER)
+                                .and_then(|s| s.to_str().ok().map(|h| h.to_string()));
+                            let body = response
+                                .collect()
+                                .await
+                                .map_err(|e| FetcherError::Hyper(e, server.unwrap_or_default()))?
+                                .aggregate();
+
+                            if self.authorize_track.is_none() {
+                                // Keep the given token until the user grant it on the Freebox
+                                let authorize =
+                                    FreeboxApiResponse::<FreeboxAuthorize>::from_reader(
+                                        body.reader(),
+                                    )?
+                                    .into_result()?
+                                    .unwrap_or_default();
+                                if !authorize.app_token.is_empty() {
+                                    let FreeboxAuthorize {
+                                        app_token,
+                                        track_id,
+                                    } = authorize;
+                                    info!(
+                                        "Freebox authorization requested, press the button on the Freebox to grant the application"
+                                    );
+                                    self.authorize_track = Some((track_id, app_token));
+
+                                    // Check the authorization status
+                                    Ok(FetchAction::Http)
+                                } else {
+                                    Err(FetcherError::Other(
+                                        "Can't request an authorization to the Freebox without `app_token`"
+                                            .to_string(),
+                                    ))
+                                }
+                            } else {
+                                let authorize_status =
+                                    FreeboxApiResponse::<FreeboxAuthorizeStatus>::from_reader(
+                                        body.reader(),
+                                    )?
+                                    .into_result()?
+                                    .unwrap_or_default();
+                                match authorize_status.status.as_str() {
+                                    "granted" => {
+                                        if let Some((_, app_token)) = self.authorize_track.take() {
+                                            self.store_app_token(app_token);
+
+                                            // Login with the new token
+                                            Ok(FetchAction::Http)
+                                        } else {
+                                            Err(FetcherError::Other(
+                                                "Freebox authorization granted without `app_token`"
+                                                    .to_string(),
+                                            ))
+                                        }
+                                    }
+                                    "pending" => {
+                                        // Wait the next fetch to check again the status
+                                        info!(
+                                            "Freebox authorization pending, press the button on the Freebox to grant the application"
+                                        );
+                                        Ok(FetchAction::None)
+                                    }
+                                    status => {
+                                        // Authorization `denied`, `timeout` or `unknown`, need to be requested again
+                                        self.authorize_track = None;
+                                        Err(FetcherError::Other(format!(
+                                            "Freebox authorization failed with status: {status}"
+                                        )))
+                                    }
+                                }
+                            }
+                        }
+                        code => Err(FetcherError::Other(format!(
+                            "Receive error from HTTP remote for authorization: {code}"
+                        ))),
+                    }
+                } else if self.challenge_freebox.is_none() {
                     match response.status() {
                         StatusCode::OK => {
                             let server = response
@@ -551,11 +2621,13 @@ where
                                 .aggregate();
 
                             // Parse the login return to get the challenge value
-                            let login_json: FreeboxApiResponse =
-                                serde_json::from_reader(body.reader())
-                                    .map_err(|e| FetcherError::Io(e.into()))?;
-                            if let Some(challenge) = login_json.get_string("challenge") {
-                                self.challenge_freebox = Some(challenge.to_string());
+                            let login = FreeboxApiResponse::<FreeboxLoginChallenge>::from_reader(
+                                body.reader(),
+                            )?
+                            .into_result()?
+                            .unwrap_or_default();
+                            if !login.challenge.is_empty() {
+                                self.challenge_freebox = Some(login.challenge);
 
                                 // Go for next call to retrieve `session_token`
                                 Ok(FetchAction::Http)
@@ -582,12 +2654,28 @@ where
                                 .map_err(|e| FetcherError::Hyper(e, server.unwrap_or_default()))?
                                 .aggregate();
 
-                            // Parse the login return to get the challenge value
-                            let login_json: FreeboxApiResponse =
-                                serde_json::from_reader(body.reader())
-                                    .map_err(|e| FetcherError::Io(e.into()))?;
-                            if let Some(token) = login_json.get_string("session_token") {
-                                self.session_token = Some(token.to_string());
+                            // Parse the session return to get the session token
+                            let session = FreeboxApiResponse::<FreeboxLoginSession>::from_reader(
+                                body.reader(),
+                            )?
+                            .into_result()?
+                            .unwrap_or_default();
+                            if !session.session_token.is_empty() {
+                                self.session_token = Some(session.session_token);
+                                self.permissions = session.permissions;
+
+                                // The permissions of the renewed session may have changed since the control action was accepted
+                                if self.state == FreeboxFetchState::Control
+                                    && self.control.as_ref().is_some_and(|control| {
+                                        !self.has_control_permission(control)
+                                    })
+                                {
+                                    if let Some(control) = self.control.take() {
+                                        self.poller.push_result(&control, false);
+                                    }
+                                    self.state = FreeboxFetchState::End;
+                                    return Ok(self.next_action());
+                                }
 
                                 // Go for next call to get all statistics
                                 Ok(FetchAction::Http)
@@ -615,76 +2703,28 @@ where
                                 .map_err(|e| FetcherError::Hyper(e, server.unwrap_or_default()))?
                                 .aggregate();
 
-                            if self.state == FreeboxFetchState::SwitchStatus {
-                                let switch_status_resp: Value =
-                                    serde_json::from_reader(body.reader())
-                                        .map_err(|e| FetcherError::Io(e.into()))?;
-                                if let Some(switch_status_array) =
-                                    switch_status_resp.get("result").and_then(|r| r.as_array())
-                                {
-                                    self.number_ports = switch_status_array.len() as u8;
-                                    let _ = self.meter_switch.send(
-                                        switch_status_array
-                                            .iter()
-                                            .map(|v| {
-                                                if let Some(value) = v.as_object() {
-                                                    value.clone()
-                                                } else {
-                                                    Map::new()
-                                                }
-                                            })
-                                            .collect(),
-                                    );
-                                    self.state = self.state.next_state(self.number_ports);
-                                    Ok(FetchAction::Http)
-                                } else {
-                                    Ok(FetchAction::None)
-                                }
-                            } else {
-                                // Parse the API response return to get the data
-                                let api_resp: FreeboxApiResponse =
-                                    serde_json::from_reader(body.reader())
-                                        .map_err(|e| FetcherError::Io(e.into()))?;
-                                if api_resp.success {
-                                    match self.state {
-                                        FreeboxFetchState::Connection => {
-                                            let _ = self.meter_conn.send(api_resp);
-                                        }
-                                        FreeboxFetchState::System => {
-                                            let _ = self.meter_system.send(api_resp);
-                                        }
-                                        FreeboxFetchState::SwitchPort(port_id) => {
-                                            let mut eth = self.meter_eth.borrow().clone();
-                                            if !eth.is_empty() {
-                                                eth[(port_id - 1) as usize] = api_resp;
-                                            } else {
-                                                eth =
-                                                    Vec::with_capacity(self.number_ports as usize);
-                                                for _ in 0..self.number_ports {
-                                                    eth.push(FreeboxApiResponse::default());
-                                                }
-                                                eth[(port_id - 1) as usize] = api_resp;
-                                            }
-                                            let _ = self.meter_eth.send(eth);
-                                        }
-                                        _ => {}
-                                    }
-                                } else {
-                                    warn!(
-                                        "API[{:?}] respond with an error: {api_resp:?}",
-                                        self.state
-                                    );
-                                    return Ok(FetchAction::None);
-                                }
+                            self.process_state_response(body)
+                        }
+                        StatusCode::FORBIDDEN | StatusCode::UNAUTHORIZED => {
+                            let server = response
+                                .headers()
+                                .get(http::header::SERVER)
+                                .and_then(|s| s.to_str().ok().map(|h| h.to_string()));
+                            let body = response
+                                .collect()
+                                .await
+                                .map_err(|e| FetcherError::Hyper(e, server.unwrap_or_default()))?
+                                .aggregate();
 
-                                self.state = self.state.next_state(self.number_ports);
-                                if self.state != FreeboxFetchState::End {
-                                    // Call for next state
-                                    Ok(FetchAction::Http)
-                                } else {
-                                    // Every call have been made
-                                    Ok(FetchAction::None)
-                                }
+                            // The Freebox give the reason of the rejection in the body
+                            match FreeboxApiResponse::<Value>::from_reader(body.reader())
+                                .and_then(|api_resp| api_resp.into_result())
+                            {
+                                Err(api_err) => self.process_api_error(api_err),
+                                Ok(_) => Err(FetcherError::Other(
+                                    "Receive HTTP rejection from remote without API error"
+                                        .to_string(),
+                                )),
                             }
                         }
                         code => Err(FetcherError::Other(format!(
@@ -705,4 +2745,894 @@ where
             Err(e) => Err(e),
         }
     }
+
+    fn process_service_response(
+        &mut self,
+        mut response: prosa::core::msg::ResponseMsg<M>,
+    ) -> Result<FetchAction<M>, FetcherError<M>> {
+        let control = self.poller.process_response(&mut response);
+        if self.state != FreeboxFetchState::End {
+            // A new fetch is already in progress, the control action is done at its end
+            self.pending_controls.extend(control);
+            return Ok(FetchAction::None);
+        }
+
+        if let Some(control) = control {
+            return Ok(self.process_control(control));
+        }
+
+        // Send the next pending event
+        Ok(self.next_action())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prosa_utils::msg::simple_string_tvf::SimpleStringTvf;
+
+    use super::*;
+    use crate::{
+        control,
+        mock::{MockRequest, MockServer, fetcher_proc, json_response, service_response, temp_path},
+    };
+
+    /// Credentials of the application in the URL, with its `app_token` as password
+    fn app_credentials() -> String {
+        format!("prosa:{}@", URL_SAFE.encode("app-token"))
+    }
+
+    /// Mock of the Freebox login API, giving a new session token at each login
+    fn login_response(
+        request: &MockRequest,
+        nb_sessions: &mut u32,
+    ) -> Option<Response<Full<Bytes>>> {
+        match request.path() {
+            "/api/v4/login/" => Some(json_response(
+                StatusCode::OK,
+                r#"{"success":true,"result":{"logged_in":false,"challenge":"challenge"}}"#,
+            )),
+            "/api/v4/login/session/" => {
+                *nb_sessions += 1;
+                Some(json_response(
+                    StatusCode::OK,
+                    &format!(
+                        r#"{{"success":true,"result":{{"session_token":"session-{nb_sessions}","challenge":"challenge","permissions":{{"settings":true}}}}}}"#
+                    ),
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    fn auth_required_response() -> Response<Full<Bytes>> {
+        json_response(
+            StatusCode::FORBIDDEN,
+            r#"{"success":false,"msg":"Erreur d'authentification de l'application","error_code":"auth_required"}"#,
+        )
+    }
+
+    #[tokio::test]
+    async fn session_renewed_on_auth_required() {
+        let mut nb_sessions = 0;
+        let server = MockServer::start(move |request| {
+            login_response(request, &mut nb_sessions).unwrap_or_else(|| {
+                if request.header("X-Fbx-App-Auth") == Some("session-1") {
+                    // The first session expires on the first call
+                    auth_required_response()
+                } else {
+                    json_response(StatusCode::OK, r#"{"success":true}"#)
+                }
+            })
+        })
+        .await;
+        let proc = fetcher_proc("freebox-renew", &server.url(&app_credentials()), None);
+        let mut adaptor = <FetcherFreeboxAdaptor as FetcherAdaptor<SimpleStringTvf>>::new(&proc)
+            .expect("Freebox adaptor");
+
+        let action = FetcherAdaptor::<SimpleStringTvf>::fetch(&mut adaptor).unwrap();
+        let action = server.run(&mut adaptor, action).await.unwrap();
+        assert!(matches!(action, FetchAction::None));
+        assert_eq!(adaptor.state, FreeboxFetchState::End);
+
+        let requests = server.requests();
+        let paths: Vec<&str> = requests.iter().map(|request| request.path()).collect();
+        assert_eq!(
+            paths[..7],
+            [
+                "/api/v4/login/",
+                "/api/v4/login/session/",
+                "/api/v4/connection/",
+                "/api/v4/login/",
+                "/api/v4/login/session/",
+                "/api/v4/connection/",
+                "/api/v4/system/",
+            ]
+        );
+        // The rejected state is replayed with the new session
+        assert_eq!(requests[5].header("X-Fbx-App-Auth"), Some("session-2"));
+        assert!(
+            requests[6..]
+                .iter()
+                .all(|request| request.header("X-Fbx-App-Auth") == Some("session-2"))
+        );
+    }
+
+    /// Create a Freebox adaptor with every control action allowed, fetched once to poll its control service
+    async fn control_adaptor(name: &str, server: &MockServer) -> FetcherFreeboxAdaptor {
+        let proc = fetcher_proc(
+            name,
+            &server.url(&app_credentials()),
+            Some("control:\n  service: FBX_CONTROL\n  reboot: true\n  wifi: true\n  wol: true"),
+        );
+        let mut adaptor = <FetcherFreeboxAdaptor as FetcherAdaptor<SimpleStringTvf>>::new(&proc)
+            .expect("Freebox adaptor");
+
+        let action = FetcherAdaptor::<SimpleStringTvf>::fetch(&mut adaptor).unwrap();
+        let FetchAction::Srv(service, msg) = server.run(&mut adaptor, action).await.unwrap() else {
+            panic!("Expected a control poll");
+        };
+        assert_eq!(service, "FBX_CONTROL");
+        assert_eq!(msg.get_string(control::ACTION).unwrap().as_ref(), "poll");
+        adaptor
+    }
+
+    /// Send the control action to the adaptor, and get the success of its result
+    async fn run_control(
+        server: &MockServer,
+        adaptor: &mut FetcherFreeboxAdaptor,
+        msg: SimpleStringTvf,
+    ) -> u64 {
+        let action = adaptor
+            .process_service_response(service_response("FBX_CONTROL", msg))
+            .unwrap();
+        let FetchAction::Srv(service, msg) = server.run(adaptor, action).await.unwrap() else {
+            panic!("Expected a control result");
+        };
+        assert_eq!(service, "FBX_CONTROL");
+        assert_eq!(msg.get_string(control::ACTION).unwrap().as_ref(), "result");
+        msg.get_unsigned(control::RESULT_SUCCESS).unwrap()
+    }
+
+    #[tokio::test]
+    async fn control_requests() {
+        let mut nb_sessions = 0;
+        let server = MockServer::start(move |request| {
+            login_response(request, &mut nb_sessions)
+                .unwrap_or_else(|| json_response(StatusCode::OK, r#"{"success":true}"#))
+        })
+        .await;
+        let mut adaptor = control_adaptor("freebox-control", &server).await;
+
+        let mut nb_requests = server.requests().len();
+        let mut msg = SimpleStringTvf::default();
+        msg.put_string(control::ACTION, "reboot");
+        assert_eq!(run_control(&server, &mut adaptor, msg).await, 1);
+        let requests = server.requests();
+        assert_eq!(requests.len(), nb_requests + 1);
+        assert_eq!(requests[nb_requests].method, Method::POST);
+        assert_eq!(requests[nb_requests].path(), "/api/v4/system/reboot/");
+        assert_eq!(
+            requests[nb_requests].header("X-Fbx-App-Auth"),
+            Some("session-1")
+        );
+
+        nb_requests = requests.len();
+        let mut msg = SimpleStringTvf::default();
+        msg.put_string(control::ACTION, "wifi");
+        msg.put_unsigned(FreeboxControl::WIFI_ENABLED, 0);
+        assert_eq!(run_control(&server, &mut adaptor, msg).await, 1);
+        let requests = server.requests();
+        assert_eq!(requests.len(), nb_requests + 1);
+        assert_eq!(requests[nb_requests].method, Method::PUT);
+        assert_eq!(requests[nb_requests].path(), "/api/v4/wifi/config/");
+        assert_eq!(
+            serde_json::from_str::<Value>(&requests[nb_requests].body).unwrap(),
+            serde_json::json!({ "enabled": false })
+        );
+
+        nb_requests = requests.len();
+        let mut msg = SimpleStringTvf::default();
+        msg.put_string(control::ACTION, "wol");
+        msg.put_string(FreeboxControl::WOL_MAC, "A4:83:E7:01:02:03");
+        msg.put_string(FreeboxControl::WOL_INTERFACE, "pub");
+        msg.put_string(FreeboxControl::WOL_PASSWORD, "secret");
+        assert_eq!(run_control(&server, &mut adaptor, msg).await, 1);
+        let requests = server.requests();
+        assert_eq!(requests.len(), nb_requests + 1);
+        assert_eq!(requests[nb_requests].method, Method::POST);
+        assert_eq!(requests[nb_requests].path(), "/api/v4/lan/wol/pub/");
+        assert_eq!(
+            serde_json::from_str::<Value>(&requests[nb_requests].body).unwrap(),
+            serde_json::json!({ "mac": "A4:83:E7:01:02:03", "password": "secret" })
+        );
+    }
+
+    #[tokio::test]
+    async fn control_rejected() {
+        let mut nb_sessions = 0;
+        let server = MockServer::start(move |request| {
+            login_response(request, &mut nb_sessions)
+                .unwrap_or_else(|| json_response(StatusCode::OK, r#"{"success":true}"#))
+        })
+        .await;
+        let proc = fetcher_proc(
+            "freebox-control-rejected",
+            &server.url(&app_credentials()),
+            Some("control:\n  service: FBX_CONTROL\n  reboot: true"),
+        );
+        let mut adaptor = <FetcherFreeboxAdaptor as FetcherAdaptor<SimpleStringTvf>>::new(&proc)
+            .expect("Freebox adaptor");
+        let action = FetcherAdaptor::<SimpleStringTvf>::fetch(&mut adaptor).unwrap();
+        assert!(matches!(
+            server.run(&mut adaptor, action).await.unwrap(),
+            FetchAction::Srv(..)
+        ));
+
+        // An action not allowed by the configuration is rejected without calling the Freebox
+        let nb_requests = server.requests().len();
+        let mut msg = SimpleStringTvf::default();
+        msg.put_string(control::ACTION, "wifi");
+        msg.put_unsigned(FreeboxControl::WIFI_ENABLED, 1);
+        assert_eq!(run_control(&server, &mut adaptor, msg).await, 0);
+        assert_eq!(server.requests().len(), nb_requests);
+    }
+
+    #[tokio::test]
+    async fn control_without_permission() {
+        let mut nb_sessions = 0;
+        let server = MockServer::start(move |request| {
+            if request.path() == "/api/v4/login/session/" {
+                json_response(
+                    StatusCode::OK,
+                    r#"{"success":true,"result":{"session_token":"session","challenge":"challenge","permissions":{"settings":false}}}"#,
+                )
+            } else {
+                login_response(request, &mut nb_sessions)
+                    .unwrap_or_else(|| json_response(StatusCode::OK, r#"{"success":true}"#))
+            }
+        })
+        .await;
+        let mut adaptor = control_adaptor("freebox-control-permission", &server).await;
+
+        // The application can't reboot without the `settings` permission
+        let nb_requests = server.requests().len();
+        let mut msg = SimpleStringTvf::default();
+        msg.put_string(control::ACTION, "reboot");
+        assert_eq!(run_control(&server, &mut adaptor, msg).await, 0);
+        assert_eq!(server.requests().len(), nb_requests);
+    }
+
+    #[tokio::test]
+    async fn control_permission_checked_after_renewal() {
+        let mut nb_sessions = 0;
+        let server = MockServer::start(move |request| match request.path() {
+            "/api/v4/login/session/" if nb_sessions > 0 => {
+                // The renewed session lost the `settings` permission
+                nb_sessions += 1;
+                json_response(
+                    StatusCode::OK,
+                    r#"{"success":true,"result":{"session_token":"session-2","challenge":"challenge","permissions":{"settings":false}}}"#,
+                )
+            }
+            "/api/v4/system/reboot/" => auth_required_response(),
+            _ => login_response(request, &mut nb_sessions)
+                .unwrap_or_else(|| json_response(StatusCode::OK, r#"{"success":true}"#)),
+        })
+        .await;
+        let mut adaptor = control_adaptor("freebox-control-renewal", &server).await;
+
+        let nb_requests = server.requests().len();
+        let mut msg = SimpleStringTvf::default();
+        msg.put_string(control::ACTION, "reboot");
+        assert_eq!(run_control(&server, &mut adaptor, msg).await, 0);
+        // The reboot is not replayed with the new session
+        assert_eq!(
+            server.paths()[nb_requests..],
+            [
+                "/api/v4/system/reboot/",
+                "/api/v4/login/",
+                "/api/v4/login/session/",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn control_during_fetch() {
+        let mut nb_sessions = 0;
+        let server = MockServer::start(move |request| {
+            login_response(request, &mut nb_sessions)
+                .unwrap_or_else(|| json_response(StatusCode::OK, r#"{"success":true}"#))
+        })
+        .await;
+        let mut adaptor = control_adaptor("freebox-control-fetch", &server).await;
+
+        // The action received during a new fetch is done at its end
+        let action = FetcherAdaptor::<SimpleStringTvf>::fetch(&mut adaptor).unwrap();
+        let mut msg = SimpleStringTvf::default();
+        msg.put_string(control::ACTION, "reboot");
+        assert!(matches!(
+            adaptor
+                .process_service_response(service_response("FBX_CONTROL", msg))
+                .unwrap(),
+            FetchAction::None
+        ));
+        let FetchAction::Srv(_, msg) = server.run(&mut adaptor, action).await.unwrap() else {
+            panic!("Expected a control result");
+        };
+        assert_eq!(msg.get_string(control::ACTION).unwrap().as_ref(), "result");
+        assert_eq!(msg.get_unsigned(control::RESULT_SUCCESS).unwrap(), 1);
+        assert_eq!(
+            server.paths().last().map(String::as_str),
+            Some("/api/v4/system/reboot/")
+        );
+    }
+
+    #[tokio::test]
+    async fn session_still_rejected_after_renewal() {
+        let mut nb_sessions = 0;
+        let server = MockServer::start(move |request| {
+            login_response(request, &mut nb_sessions).unwrap_or_else(auth_required_response)
+        })
+        .await;
+        let proc = fetcher_proc("freebox-rejected", &server.url(&app_credentials()), None);
+        let mut adaptor = <FetcherFreeboxAdaptor as FetcherAdaptor<SimpleStringTvf>>::new(&proc)
+            .expect("Freebox adaptor");
+
+        let action = FetcherAdaptor::<SimpleStringTvf>::fetch(&mut adaptor).unwrap();
+        match server.run(&mut adaptor, action).await {
+            Err(FetcherError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::PermissionDenied),
+            Err(e) => panic!("Unexpected error {e:?}"),
+            Ok(action) => panic!("Unexpected action {action:?}"),
+        }
+        assert_eq!(
+            server.paths(),
+            [
+                "/api/v4/login/",
+                "/api/v4/login/session/",
+                "/api/v4/connection/",
+                "/api/v4/login/",
+                "/api/v4/login/session/",
+                "/api/v4/connection/",
+            ]
+        );
+    }
+
+    /// Run a fetch that ask an authorization to the Freebox, answered with the `status`
+    async fn authorize(
+        status: &'static str,
+    ) -> (
+        MockServer,
+        FetcherFreeboxAdaptor,
+        Result<FetchAction<SimpleStringTvf>, FetcherError<SimpleStringTvf>>,
+        PathBuf,
+    ) {
+        let app_token_file = temp_path(&format!("freebox-authorize-{status}.token"));
+        let _ = std::fs::remove_file(&app_token_file);
+
+        let mut nb_sessions = 0;
+        let server = MockServer::start(move |request| {
+            login_response(request, &mut nb_sessions).unwrap_or_else(|| match request.path() {
+                "/api/v4/login/authorize/" => json_response(
+                    StatusCode::OK,
+                    r#"{"success":true,"result":{"app_token":"new-app-token","track_id":42}}"#,
+                ),
+                "/api/v4/login/authorize/42" => json_response(
+                    StatusCode::OK,
+                    &format!(
+                        r#"{{"success":true,"result":{{"status":"{status}","challenge":"challenge"}}}}"#
+                    ),
+                ),
+                _ => json_response(StatusCode::OK, r#"{"success":true}"#),
+            })
+        })
+        .await;
+        let proc = fetcher_proc(
+            &format!("freebox-authorize-{status}"),
+            &server.url("prosa@"),
+            Some(&format!("app_token_file: {}", app_token_file.display())),
+        );
+        let mut adaptor = <FetcherFreeboxAdaptor as FetcherAdaptor<SimpleStringTvf>>::new(&proc)
+            .expect("Freebox adaptor");
+        assert!(adaptor.need_authorization());
+
+        let action = FetcherAdaptor::<SimpleStringTvf>::fetch(&mut adaptor).unwrap();
+        let result = server.run(&mut adaptor, action).await;
+        (server, adaptor, result, app_token_file)
+    }
+
+    #[tokio::test]
+    async fn authorization_pending() {
+        let (server, adaptor, result, app_token_file) = authorize("pending").await;
+        assert!(matches!(result, Ok(FetchAction::None)));
+        assert_eq!(
+            server.paths(),
+            ["/api/v4/login/authorize/", "/api/v4/login/authorize/42"]
+        );
+        assert_eq!(
+            adaptor.authorize_track,
+            Some((42, "new-app-token".to_string()))
+        );
+        assert!(adaptor.need_authorization());
+        assert!(!app_token_file.exists());
+    }
+
+    #[tokio::test]
+    async fn authorization_granted() {
+        let (server, adaptor, result, app_token_file) = authorize("granted").await;
+        assert!(matches!(result, Ok(FetchAction::None)));
+        assert_eq!(
+            server.paths()[..4],
+            [
+                "/api/v4/login/authorize/",
+                "/api/v4/login/authorize/42",
+                "/api/v4/login/",
+                "/api/v4/login/session/",
+            ]
+        );
+        assert_eq!(adaptor.app_token.as_deref(), Some("new-app-token"));
+        assert!(!adaptor.need_authorization());
+        assert_eq!(
+            std::fs::read_to_string(&app_token_file).unwrap(),
+            "new-app-token"
+        );
+        let _ = std::fs::remove_file(&app_token_file);
+    }
+
+    #[tokio::test]
+    async fn authorization_denied() {
+        let (_server, adaptor, result, app_token_file) = authorize("denied").await;
+        assert!(
+            matches!(result, Err(FetcherError::Other(msg)) if msg == "Freebox authorization failed with status: denied")
+        );
+        assert_eq!(adaptor.authorize_track, None);
+        assert!(adaptor.need_authorization());
+        assert!(!app_token_file.exists());
+    }
+
+    #[tokio::test]
+    async fn authorization_timeout() {
+        let (_server, adaptor, result, app_token_file) = authorize("timeout").await;
+        assert!(
+            matches!(result, Err(FetcherError::Other(msg)) if msg == "Freebox authorization failed with status: timeout")
+        );
+        assert_eq!(adaptor.authorize_track, None);
+        assert!(adaptor.need_authorization());
+        assert!(!app_token_file.exists());
+    }
+
+    /// Parse the result of a captured Freebox API response
+    fn parse_fixture<T>(json: &[u8]) -> T
+    where
+        T: DeserializeOwned,
+    {
+        FreeboxApiResponse::<T>::from_reader(json)
+            .unwrap()
+            .into_result()
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_connection() {
+        let conn: FreeboxConnection =
+            parse_fixture(include_bytes!("../tests/fixtures/freebox/connection.json"));
+        assert_eq!(conn.conn_type, "ethernet");
+        assert_eq!(conn.media, "ftth");
+        assert_eq!(conn.get_state(), 3);
+        assert_eq!(conn.rate_down, Some(12380));
+        assert_eq!(conn.bandwidth_down, Some(1_000_000_000));
+        assert_eq!(conn.bytes_up, Some(3_172_519_012));
+        assert_eq!(conn.bytes_down, Some(25_416_321_848));
+    }
+
+    #[test]
+    fn parse_connection_xdsl() {
+        let xdsl: FreeboxXdsl = parse_fixture(include_bytes!(
+            "../tests/fixtures/freebox/connection_xdsl.json"
+        ));
+        assert_eq!(xdsl.status.status, "showtime");
+        assert_eq!(xdsl.status.uptime, Some(1_210_356));
+        assert_eq!(xdsl.down.maxrate, Some(61440));
+        assert_eq!(xdsl.down.rate, Some(58936));
+        assert_eq!(xdsl.down.get_snr(), Some(6.2));
+        assert_eq!(xdsl.down.get_attn(), Some(18.3));
+        assert_eq!(xdsl.down.crc, Some(12));
+        assert_eq!(xdsl.up.get_snr(), Some(10.3));
+    }
+
+    #[test]
+    fn parse_connection_ftth() {
+        let ftth: FreeboxFtth = parse_fixture(include_bytes!(
+            "../tests/fixtures/freebox/connection_ftth.json"
+        ));
+        assert!(ftth.sfp_present && ftth.sfp_has_signal && ftth.link);
+        assert_eq!(ftth.sfp_vendor, "FREEBOX");
+        assert_eq!(ftth.sfp_pwr_tx, Some(243));
+        assert_eq!(ftth.sfp_pwr_rx, Some(-1789));
+    }
+
+    #[test]
+    fn parse_system() {
+        let system: FreeboxSystem =
+            parse_fixture(include_bytes!("../tests/fixtures/freebox/system.json"));
+        assert_eq!(system.board_name, "fbxgw7r");
+        assert_eq!(system.firmware_version, "4.8.7");
+        // The uptime is sent as a string
+        assert_eq!(system.uptime_val, Some(1_232_591));
+        assert_eq!(system.temp_cpum, Some(62));
+        assert_eq!(system.temp_t1, None);
+        assert_eq!(system.fan_rpm, Some(1450));
+    }
+
+    #[test]
+    fn parse_switch() {
+        let ports: Vec<FreeboxSwitchPort> = parse_fixture(include_bytes!(
+            "../tests/fixtures/freebox/switch_status.json"
+        ));
+        assert_eq!(ports.len(), 3);
+        assert_eq!(ports[0].speed, Some(1000));
+        assert_eq!(
+            ports[0].get_labels(&[]),
+            [
+                KeyValue::new("port", "1"),
+                KeyValue::new("host", "00:11:32:AA:BB:CC")
+            ]
+        );
+        // No host label when several hosts are attached to the port
+        assert_eq!(ports[1].get_labels(&[]), [KeyValue::new("port", "2")]);
+        assert_eq!(ports[2].link, "down");
+        assert!(ports[2].mac_list.is_empty());
+
+        let stats: FreeboxSwitchPortStats = parse_fixture(include_bytes!(
+            "../tests/fixtures/freebox/switch_port_stats.json"
+        ));
+        assert_eq!(stats.rx_good_bytes, Some(3_427_823_412));
+        assert_eq!(stats.rx_err_packets, Some(3));
+        assert_eq!(stats.tx_bytes, Some(18_223_491_821));
+        assert_eq!(stats.tx_collisions, Some(0));
+    }
+
+    #[test]
+    fn parse_wifi() {
+        let aps: Vec<FreeboxWifiAp> =
+            parse_fixture(include_bytes!("../tests/fixtures/freebox/wifi_ap.json"));
+        assert_eq!(aps.len(), 2);
+        assert_eq!(aps[1].status.primary_channel, Some(36));
+        assert_eq!(aps[1].status.channel_width, Some(80));
+
+        let stations: Vec<FreeboxWifiStation> = parse_fixture(include_bytes!(
+            "../tests/fixtures/freebox/wifi_stations.json"
+        ));
+        assert_eq!(stations.len(), 2);
+        assert_eq!(stations[0].mac, "A4:83:E7:01:02:03");
+        assert_eq!(stations[0].signal, Some(-58));
+        assert_eq!(stations[0].rx_rate, Some(722));
+
+        let channel_usages: Vec<FreeboxWifiChannelUsage> = parse_fixture(include_bytes!(
+            "../tests/fixtures/freebox/wifi_channel_usage.json"
+        ));
+        assert_eq!(channel_usages[0].channel, 36);
+        assert_eq!(channel_usages[0].busy_percent, Some(12));
+
+        let bss: Vec<FreeboxWifiBss> =
+            parse_fixture(include_bytes!("../tests/fixtures/freebox/wifi_bss.json"));
+        assert_eq!(bss[1].phy_id, 1);
+        assert_eq!(bss[1].config.ssid, "Freebox-123456");
+        assert_eq!(bss[1].status.authorized_sta_count, 2);
+
+        let wifi = FreeboxWifi {
+            aps,
+            bss,
+            ..Default::default()
+        };
+        assert_eq!(wifi.get_band(0).as_deref(), Some("2.4GHz"));
+        assert_eq!(wifi.get_band(1).as_deref(), Some("5GHz"));
+        assert_eq!(wifi.get_band(2), None);
+    }
+
+    #[test]
+    fn parse_lan_browser() {
+        let lan_hosts: Vec<FreeboxLanHost> =
+            parse_fixture(include_bytes!("../tests/fixtures/freebox/lan_browser.json"));
+        assert_eq!(lan_hosts.len(), 2);
+        assert_eq!(lan_hosts[0].get_mac(), "A4:83:E7:01:02:03");
+        assert_eq!(lan_hosts[0].primary_name, "iPhone");
+        assert!(lan_hosts[0].reachable);
+        assert!(!lan_hosts[1].reachable);
+    }
+
+    #[test]
+    fn parse_call_log() {
+        let calls: Vec<FreeboxCall> =
+            parse_fixture(include_bytes!("../tests/fixtures/freebox/call_log.json"));
+        assert_eq!(calls.len(), 3);
+        assert_eq!(calls[0].id, 52);
+        assert_eq!(calls[0].call_type, "missed");
+        assert!(calls[0].new);
+        assert_eq!(calls[1].name, "Maison");
+        assert_eq!(calls[1].duration, 124);
+    }
+
+    /// Create a Freebox adaptor sending its events to the `HOME_EVENT` service
+    fn event_adaptor(name: &str) -> FetcherFreeboxAdaptor {
+        let proc = fetcher_proc(
+            name,
+            &format!("http://{}127.0.0.1", app_credentials()),
+            Some("event_service: HOME_EVENT"),
+        );
+        <FetcherFreeboxAdaptor as FetcherAdaptor<SimpleStringTvf>>::new(&proc)
+            .expect("Freebox adaptor")
+    }
+
+    /// Getter of the events sent by the adaptor at the end of its fetch
+    fn sent_events(adaptor: &mut FetcherFreeboxAdaptor) -> Vec<SimpleStringTvf> {
+        let mut events = Vec::new();
+        while let FetchAction::Srv(service, msg) = adaptor.next_action::<SimpleStringTvf>() {
+            assert_eq!(service, "HOME_EVENT");
+            events.push(msg);
+        }
+        events
+    }
+
+    #[test]
+    fn presence_events() {
+        let mut adaptor = event_adaptor("freebox-presence");
+        let mut lan_hosts: Vec<FreeboxLanHost> =
+            parse_fixture(include_bytes!("../tests/fixtures/freebox/lan_browser.json"));
+
+        // The hosts of the first snapshot are not notified
+        adaptor.detect_presence(&lan_hosts);
+        assert!(sent_events(&mut adaptor).is_empty());
+
+        // The iPhone leaves and the NAS arrives
+        lan_hosts[0].reachable = false;
+        lan_hosts[1].reachable = true;
+        adaptor.detect_presence(&lan_hosts);
+        let events = sent_events(&mut adaptor);
+        assert_eq!(events.len(), 2);
+        for (event, mac, name, vendor, reachable) in [
+            (&events[0], "A4:83:E7:01:02:03", "iPhone", "Apple, Inc.", 0),
+            (
+                &events[1],
+                "00:11:32:AA:BB:CC",
+                "nas",
+                "Synology Incorporated",
+                1,
+            ),
+        ] {
+            assert_eq!(
+                event.get_string(FreeboxEvent::EVENT_TYPE).unwrap().as_ref(),
+                "presence"
+            );
+            assert_eq!(
+                event
+                    .get_string(FreeboxEvent::PRESENCE_MAC)
+                    .unwrap()
+                    .as_ref(),
+                mac
+            );
+            assert_eq!(
+                event
+                    .get_string(FreeboxEvent::PRESENCE_NAME)
+                    .unwrap()
+                    .as_ref(),
+                name
+            );
+            assert_eq!(
+                event
+                    .get_string(FreeboxEvent::PRESENCE_VENDOR)
+                    .unwrap()
+                    .as_ref(),
+                vendor
+            );
+            assert_eq!(
+                event
+                    .get_unsigned(FreeboxEvent::PRESENCE_REACHABLE)
+                    .unwrap(),
+                reachable
+            );
+        }
+
+        // No event without change
+        adaptor.detect_presence(&lan_hosts);
+        assert!(sent_events(&mut adaptor).is_empty());
+    }
+
+    #[test]
+    fn call_events() {
+        let mut adaptor = event_adaptor("freebox-calls");
+        let mut calls: Vec<FreeboxCall> =
+            parse_fixture(include_bytes!("../tests/fixtures/freebox/call_log.json"));
+
+        // The calls already in the log are counted, but not notified
+        adaptor.detect_calls(calls.clone());
+        assert!(sent_events(&mut adaptor).is_empty());
+        assert_eq!(adaptor.last_call_id, Some(52));
+
+        // Only the calls newer than the last seen one are notified, from the oldest
+        calls.insert(
+            0,
+            FreeboxCall {
+                id: 54,
+                call_type: "accepted".to_string(),
+                number: "0123456789".to_string(),
+                name: "Maison".to_string(),
+                duration: 62,
+                datetime: 1760605200,
+                new: false,
+            },
+        );
+        calls.insert(
+            1,
+            FreeboxCall {
+                id: 53,
+                call_type: "missed".to_string(),
+                number: "0611223344".to_string(),
+                name: "0611223344".to_string(),
+                duration: 0,
+                datetime: 1760601600,
+                new: true,
+            },
+        );
+        adaptor.detect_calls(calls.clone());
+        let events = sent_events(&mut adaptor);
+        assert_eq!(events.len(), 2);
+        for (event, call_type, number, name, duration, datetime) in [
+            (
+                &events[0],
+                "missed",
+                "0611223344",
+                "0611223344",
+                0,
+                1760601600,
+            ),
+            (
+                &events[1],
+                "accepted",
+                "0123456789",
+                "Maison",
+                62,
+                1760605200,
+            ),
+        ] {
+            assert_eq!(
+                event.get_string(FreeboxEvent::EVENT_TYPE).unwrap().as_ref(),
+                "call"
+            );
+            assert_eq!(
+                event.get_string(FreeboxEvent::CALL_TYPE).unwrap().as_ref(),
+                call_type
+            );
+            assert_eq!(
+                event
+                    .get_string(FreeboxEvent::CALL_NUMBER)
+                    .unwrap()
+                    .as_ref(),
+                number
+            );
+            assert_eq!(
+                event.get_string(FreeboxEvent::CALL_NAME).unwrap().as_ref(),
+                name
+            );
+            assert_eq!(
+                event.get_unsigned(FreeboxEvent::CALL_DURATION).unwrap(),
+                duration
+            );
+            assert_eq!(
+                event.get_unsigned(FreeboxEvent::CALL_DATETIME).unwrap(),
+                datetime
+            );
+        }
+        assert_eq!(adaptor.last_call_id, Some(54));
+        assert_eq!(adaptor.meter_calls.borrow().get("missed"), Some(&2));
+        assert_eq!(adaptor.meter_calls.borrow().get("accepted"), Some(&2));
+        assert_eq!(adaptor.meter_calls.borrow().get("outgoing"), Some(&1));
+
+        // No event without new call
+        adaptor.detect_calls(calls);
+        assert!(sent_events(&mut adaptor).is_empty());
+    }
+
+    #[test]
+    fn parse_storage() {
+        let disks: Vec<FreeboxDisk> = parse_fixture(include_bytes!(
+            "../tests/fixtures/freebox/storage_disk.json"
+        ));
+        assert_eq!(disks[0].id, 1000);
+        assert_eq!(disks[0].disk_type, "sata");
+        assert_eq!(disks[0].temp, 38);
+        assert!(disks[0].spinning);
+
+        let partitions: Vec<FreeboxPartition> = parse_fixture(include_bytes!(
+            "../tests/fixtures/freebox/storage_partition.json"
+        ));
+        assert_eq!(partitions[0].disk_id, 1000);
+        assert_eq!(partitions[0].label, "Disque dur");
+        assert_eq!(partitions[0].total_bytes, Some(983_351_087_104));
+        assert_eq!(partitions[0].used_bytes, Some(61_829_431_296));
+        assert_eq!(partitions[0].free_bytes, Some(871_522_127_872));
+    }
+
+    #[test]
+    fn parse_api_error() {
+        let err = FreeboxApiResponse::<Value>::from_reader(
+            include_bytes!("../tests/fixtures/freebox/auth_required.json").as_slice(),
+        )
+        .unwrap()
+        .into_result()
+        .unwrap_err();
+        assert_eq!(
+            err,
+            FreeboxApiError::AuthRequired("Erreur d'authentification de l'application".to_string())
+        );
+        assert!(err.is_session_expired());
+
+        let err = FreeboxApiResponse::<Vec<FreeboxCall>>::from_reader(
+            include_bytes!("../tests/fixtures/freebox/insufficient_rights.json").as_slice(),
+        )
+        .unwrap()
+        .into_result()
+        .unwrap_err();
+        assert!(matches!(err, FreeboxApiError::InsufficientRights(_)));
+        assert!(!err.is_session_expired());
+
+        assert!(matches!(
+            FreeboxApiResponse::<Value>::from_reader(b"<html></html>".as_slice()),
+            Err(FreeboxApiError::InvalidResponse(_))
+        ));
+    }
+
+    #[test]
+    fn api_error_mapping() {
+        for (error_code, session_expired, kind) in [
+            ("auth_required", true, io::ErrorKind::PermissionDenied),
+            ("invalid_session", true, io::ErrorKind::PermissionDenied),
+            ("invalid_token", true, io::ErrorKind::PermissionDenied),
+            ("pending_token", false, io::ErrorKind::PermissionDenied),
+            (
+                "insufficient_rights",
+                false,
+                io::ErrorKind::PermissionDenied,
+            ),
+            (
+                "denied_from_external_ip",
+                false,
+                io::ErrorKind::PermissionDenied,
+            ),
+            ("new_apps_denied", false, io::ErrorKind::PermissionDenied),
+            ("apps_denied", false, io::ErrorKind::PermissionDenied),
+            ("invalid_request", false, io::ErrorKind::InvalidInput),
+            ("ratelimited", false, io::ErrorKind::WouldBlock),
+            ("internal_error", false, io::ErrorKind::Interrupted),
+            ("nodev", false, io::ErrorKind::Other),
+        ] {
+            let api_err =
+                FreeboxApiError::new(Some(error_code.to_string()), Some("msg".to_string()));
+            assert!(
+                !matches!(api_err, FreeboxApiError::Other { .. }) || error_code == "nodev",
+                "`{error_code}` is not mapped"
+            );
+            assert_eq!(
+                api_err.is_session_expired(),
+                session_expired,
+                "{error_code}"
+            );
+
+            match FetcherError::<SimpleStringTvf>::from(api_err.clone()) {
+                FetcherError::Io(e) => {
+                    assert_eq!(e.kind(), kind, "{error_code}");
+                    assert_eq!(e.to_string(), api_err.to_string());
+                }
+                e => panic!("Unexpected error {e:?} for `{error_code}`"),
+            }
+        }
+
+        assert_eq!(
+            FreeboxApiError::new(None, None),
+            FreeboxApiError::Other {
+                code: "unknown".to_string(),
+                msg: String::new(),
+            }
+        );
+    }
 }
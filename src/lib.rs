@@ -1,5 +1,9 @@
 //! Group of adaptor useful to automate home
 
 pub mod bbox;
+pub mod control;
 pub mod deye_solar;
 pub mod freebox;
+
+#[cfg(test)]
+mod mock;
@@ -0,0 +1,220 @@
+//! Mock servers and helpers used to test the adaptors
+
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use bytes::Bytes;
+use http::{HeaderMap, Method, Request, Response, StatusCode, Uri};
+use http_body_util::{BodyExt as _, Full};
+use hyper::{body::Incoming, service::service_fn};
+use hyper_util::rt::TokioIo;
+use prosa::core::{
+    main::{MainProc, MainRunnable as _},
+    msg::{RequestMsg, ResponseMsg},
+    proc::ProcConfig as _,
+    settings::settings,
+};
+use prosa_fetcher::{
+    adaptor::FetcherAdaptor,
+    proc::{FetchAction, FetcherError, FetcherProc},
+};
+use prosa_utils::msg::simple_string_tvf::SimpleStringTvf;
+use serde::Serialize;
+use tokio::net::{TcpListener, TcpStream};
+
+/// Maximum number of HTTP calls for a single action, to detect loops
+const MAX_HTTP_CALLS: usize = 100;
+
+#[settings]
+#[derive(Default, Debug, Serialize)]
+struct MockSettings {}
+
+/// Request received by the mock server
+#[derive(Debug, Clone)]
+pub(crate) struct MockRequest {
+    pub method: Method,
+    pub uri: Uri,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+impl MockRequest {
+    /// Getter of the request path
+    pub fn path(&self) -> &str {
+        self.uri.path()
+    }
+
+    /// Getter of a query parameter
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.uri.query()?.split('&').find_map(|param| {
+            param
+                .split_once('=')
+                .filter(|(key, _)| *key == name)
+                .map(|(_, value)| value)
+        })
+    }
+
+    /// Getter of a header value
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|h| h.to_str().ok())
+    }
+}
+
+/// Build a response with a JSON body
+pub(crate) fn json_response(status: StatusCode, body: &str) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::copy_from_slice(body.as_bytes())))
+        .unwrap()
+}
+
+/// HTTP server answering the requests with a handler, and recording them
+pub(crate) struct MockServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl MockServer {
+    /// Start the mock server on a local port
+    pub async fn start<F>(handler: F) -> MockServer
+    where
+        F: FnMut(&MockRequest) -> Response<Full<Bytes>> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler = Arc::new(Mutex::new(handler));
+
+        let server_requests = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let requests = server_requests.clone();
+                let handler = handler.clone();
+                tokio::spawn(async move {
+                    let service = service_fn(move |request: Request<Incoming>| {
+                        let requests = requests.clone();
+                        let handler = handler.clone();
+                        async move {
+                            let (parts, body) = request.into_parts();
+                            let body = body.collect().await.unwrap().to_bytes();
+                            let request = MockRequest {
+                                method: parts.method,
+                                uri: parts.uri,
+                                headers: parts.headers,
+                                body: String::from_utf8_lossy(&body).into_owned(),
+                            };
+                            let response = (handler.lock().unwrap())(&request);
+                            requests.lock().unwrap().push(request);
+                            Ok::<_, Infallible>(response)
+                        }
+                    });
+                    let _ = hyper::server::conn::http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await;
+                });
+            }
+        });
+
+        MockServer { addr, requests }
+    }
+
+    /// Getter of the server URL with the given credentials (`user:password@`)
+    pub fn url(&self, credentials: &str) -> String {
+        format!("http://{credentials}{}", self.addr)
+    }
+
+    /// Getter of the requests received by the server
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Getter of the paths requested to the server
+    pub fn paths(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|request| request.path().to_string())
+            .collect()
+    }
+
+    /// Send the HTTP calls of the adaptor to the server, until the adaptor asks for another action
+    pub async fn run<A>(
+        &self,
+        adaptor: &mut A,
+        mut action: FetchAction<SimpleStringTvf>,
+    ) -> Result<FetchAction<SimpleStringTvf>, FetcherError<SimpleStringTvf>>
+    where
+        A: FetcherAdaptor<SimpleStringTvf>,
+    {
+        let mut nb_calls = 0;
+        while let FetchAction::Http = action {
+            nb_calls += 1;
+            assert!(nb_calls <= MAX_HTTP_CALLS, "Too many HTTP calls");
+
+            let request = adaptor.create_http_request(
+                Request::builder().header(hyper::header::HOST, self.addr.to_string()),
+            )?;
+            let stream = TcpStream::connect(self.addr).await?;
+            let (mut sender, connection) =
+                hyper::client::conn::http1::handshake(TokioIo::new(stream))
+                    .await
+                    .map_err(|e| FetcherError::Hyper(e, self.addr.to_string()))?;
+            tokio::spawn(connection);
+
+            let response = sender
+                .send_request(request)
+                .await
+                .map_err(|e| FetcherError::Hyper(e, self.addr.to_string()));
+            action = adaptor.process_http_response(response).await?;
+        }
+
+        Ok(action)
+    }
+}
+
+/// Build the response of a service to the adaptor
+pub(crate) fn service_response(
+    service: &str,
+    data: SimpleStringTvf,
+) -> ResponseMsg<SimpleStringTvf> {
+    let request = RequestMsg::new(
+        service.to_string(),
+        SimpleStringTvf::default(),
+        tokio::sync::oneshot::channel().0,
+    );
+    ResponseMsg::from_request(request, data)
+}
+
+/// Getter of a temporary file path unique for a test
+pub(crate) fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("prosa-adaptor-home-{}-{name}", std::process::id()))
+}
+
+/// Create a fetcher processor targeting the URL, with an optional adaptor configuration (YAML)
+pub(crate) fn fetcher_proc(
+    name: &str,
+    url: &str,
+    adaptor_config: Option<&str>,
+) -> FetcherProc<SimpleStringTvf> {
+    let (bus, _main) = MainProc::<SimpleStringTvf>::create(&MockSettings::default(), Some(1));
+
+    let mut settings = serde_json::json!({ "target": { "url": url } });
+    if let Some(adaptor_config) = adaptor_config {
+        let config_path = temp_path(&format!("{name}.yaml"));
+        std::fs::write(&config_path, adaptor_config).unwrap();
+        settings["adaptor_config_path"] = config_path.to_string_lossy().into();
+    }
+
+    FetcherProc::<SimpleStringTvf>::create(
+        1,
+        name.to_string(),
+        bus,
+        serde_json::from_value(settings).unwrap(),
+    )
+}